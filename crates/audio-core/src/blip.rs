@@ -0,0 +1,197 @@
+use std::f64::consts::PI;
+
+/// half the taps of the step kernel, also the output delay in samples
+const HALF_WIDTH: usize = 16;
+const WIDTH: usize = 2 * HALF_WIDTH;
+/// sub-sample positions a step can land on
+const PHASES: usize = 32;
+/// kernel cutoff as a fraction of the output nyquist frequency
+const CUTOFF: f64 = 0.9;
+
+/// BlipBuffer
+///
+/// band-limited step synthesis in the style of blip_buf. a sound chip's
+/// output is described by the changes in its level, at the times of its
+/// own clock, and sampled at the output rate without the aliasing that
+/// sampling a square wave directly gives.
+///
+/// each change is spread over `WIDTH` samples by a windowed sinc kernel,
+/// output is delayed by `HALF_WIDTH` samples to make room for it.
+#[derive(Debug, Clone)]
+pub struct BlipBuffer {
+    /// output samples per clock
+    factor: f64,
+    /// output position of the current frame's clock 0
+    offset: f64,
+    /// samples of the band-limited impulses, summed on the way out
+    buf: Vec<f32>,
+    integrator: f32,
+    /// kernel taps for every phase
+    kernel: Vec<[f32; WIDTH]>,
+}
+
+impl BlipBuffer {
+    /// steps timed in a `clock_rate` hz clock, read at `sample_rate` hz
+    pub fn new(clock_rate: f64, sample_rate: u32) -> Self {
+        Self {
+            factor: sample_rate as f64 / clock_rate,
+            offset: 0.0,
+            buf: vec![],
+            integrator: 0.0,
+            kernel: (0..=PHASES).map(kernel).collect(),
+        }
+    }
+
+    /// the level changes by `delta` at `time` clocks into the frame
+    pub fn add_delta(&mut self, time: u32, delta: f32) {
+        let pos = self.offset + time as f64 * self.factor;
+        let i = pos as usize;
+        let phase = ((pos - i as f64) * PHASES as f64).round() as usize;
+        if self.buf.len() < i + WIDTH {
+            self.buf.resize(i + WIDTH, 0.0);
+        }
+        for (b, k) in self.buf[i..i + WIDTH].iter_mut().zip(&self.kernel[phase]) {
+            *b += delta * k;
+        }
+    }
+
+    /// the frame ran for `duration` clocks, its samples can be read
+    pub fn end_frame(&mut self, duration: u32) {
+        self.offset += duration as f64 * self.factor;
+        if self.buf.len() < self.samples_avail() + WIDTH {
+            self.buf.resize(self.samples_avail() + WIDTH, 0.0);
+        }
+    }
+
+    /// samples no later step can change
+    pub fn samples_avail(&self) -> usize {
+        self.offset as usize
+    }
+
+    /// take up to `out.len()` samples, returns the number taken
+    pub fn read_samples(&mut self, out: &mut [f32]) -> usize {
+        let n = out.len().min(self.samples_avail());
+        for (o, &b) in out.iter_mut().zip(&self.buf[..n]) {
+            self.integrator += b;
+            *o = self.integrator;
+        }
+        self.buf.drain(..n);
+        self.offset -= n as f64;
+        n
+    }
+
+    /// drop everything, back to silence
+    pub fn clear(&mut self) {
+        self.offset = 0.0;
+        self.buf.clear();
+        self.integrator = 0.0;
+    }
+}
+
+/// band-limited step for a step `phase / PHASES` of a sample late, as the
+/// difference it makes to each sample. the samples add back up to the step,
+/// so the differences are the impulse integrated over each sample rather
+/// than sampled, which would lift the treble. they sum to exactly 1 so
+/// steps settle at their full height.
+fn kernel(phase: usize) -> [f32; WIDTH] {
+    /// points the impulse is integrated over in a sample
+    const STEPS: usize = 16;
+    let frac = phase as f64 / PHASES as f64;
+    let mut taps = [0.0; WIDTH];
+    for (j, tap) in taps.iter_mut().enumerate() {
+        let end = j as f64 - HALF_WIDTH as f64 - frac;
+        let area: f64 = (0..STEPS)
+            .map(|k| impulse(end - (k as f64 + 0.5) / STEPS as f64))
+            .sum();
+        *tap = (area / STEPS as f64) as f32;
+    }
+    let sum: f32 = taps.iter().sum();
+    taps.iter_mut().for_each(|t| *t /= sum);
+    taps[HALF_WIDTH] += 1.0 - taps.iter().sum::<f32>();
+    taps
+}
+
+/// blackman windowed sinc, `t` samples from the step
+fn impulse(t: f64) -> f64 {
+    if t.abs() >= HALF_WIDTH as f64 {
+        return 0.0;
+    }
+    let x = PI * t * CUTOFF;
+    let sinc = if x == 0.0 { 1.0 } else { x.sin() / x };
+    let w = (t / HALF_WIDTH as f64 + 1.0) * PI;
+    sinc * (0.42 - 0.5 * w.cos() + 0.08 * (2.0 * w).cos())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::spectrum::amplitude;
+
+    const CLOCK: f64 = 1_000_000.0;
+    const RATE: u32 = 50_000;
+    /// a 3086.4hz square wave, 17th harmonic aliases to 2469.1hz at 50khz
+    const HALF_PERIOD: u32 = 162;
+
+    fn square_blip(frames: u32) -> Vec<f32> {
+        let mut blip = BlipBuffer::new(CLOCK, RATE);
+        let mut level = -0.5;
+        let mut t = 0;
+        for _ in 0..frames {
+            // 1/100 s frames, steps carried over frame edges
+            while t < 10_000 {
+                blip.add_delta(t, -2.0 * level);
+                level = -level;
+                t += HALF_PERIOD;
+            }
+            t -= 10_000;
+            blip.end_frame(10_000);
+        }
+        let mut out = vec![0.0; blip.samples_avail()];
+        blip.read_samples(&mut out);
+        out
+    }
+
+    fn square_naive(samples: usize) -> Vec<f32> {
+        (0..samples)
+            .map(|i| {
+                let clock = (i as f64 * CLOCK / RATE as f64) as u32;
+                match clock / HALF_PERIOD % 2 {
+                    0 => 0.5,
+                    _ => -0.5,
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn steps_settle_at_their_height() {
+        let mut blip = BlipBuffer::new(CLOCK, RATE);
+        blip.add_delta(7, 0.75);
+        blip.end_frame(2000);
+        let mut out = [0.0; 100];
+        assert_eq!(blip.read_samples(&mut out), 100);
+        // ringing only inside the kernel's width
+        assert!(out[..HALF_WIDTH / 2].iter().all(|s| s.abs() < 0.01));
+        assert!(out[WIDTH..].iter().all(|s| (s - 0.75).abs() < 1e-6));
+    }
+
+    #[test]
+    fn square_wave_has_no_aliases() {
+        let blip = square_blip(20);
+        let naive = square_naive(blip.len());
+        let fundamental = CLOCK / (2 * HALF_PERIOD) as f64;
+        let alias = 17.0 * fundamental - RATE as f64;
+
+        // odd harmonics at 4 / (pi k) of the half amplitude
+        let a1 = amplitude(&blip, RATE, fundamental);
+        let a3 = amplitude(&blip, RATE, 3.0 * fundamental);
+        assert!((a1 - 2.0 / PI as f32).abs() < 0.01, "{a1}");
+        assert!((a3 - 2.0 / (3.0 * PI) as f32).abs() < 0.01, "{a3}");
+        assert!(amplitude(&blip, RATE, 2.0 * fundamental) < 1e-3);
+
+        let blip_alias = amplitude(&blip, RATE, -alias);
+        let naive_alias = amplitude(&naive, RATE, -alias);
+        assert!(naive_alias > 0.02, "{naive_alias}");
+        assert!(blip_alias < 1e-3, "{blip_alias}");
+    }
+}
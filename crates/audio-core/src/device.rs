@@ -0,0 +1,95 @@
+//! the default sound card, through cpal
+
+use std::sync::{Arc, Mutex};
+
+use color_eyre::{
+    eyre::{bail, eyre},
+    Result,
+};
+use cpal::{
+    traits::{DeviceTrait, HostTrait, StreamTrait},
+    SampleFormat, Stream,
+};
+use tracing::warn;
+
+use crate::{AudioSink, Format, RingBuffer, Stats};
+
+/// DeviceSink
+///
+/// plays on the default output device. samples wait in a `RingBuffer`
+/// for the card's callback, `latency` worth of frames of it.
+pub struct DeviceSink {
+    format: Format,
+    ring: Arc<Mutex<RingBuffer>>,
+    // playback stops when the stream is dropped
+    _stream: Stream,
+}
+
+impl DeviceSink {
+    /// open the default device at its preferred rate, buffering up to
+    /// `latency` seconds
+    pub fn open(latency: f32) -> Result<Self> {
+        let device = cpal::default_host()
+            .default_output_device()
+            .ok_or_else(|| eyre!("no audio output device"))?;
+        let config = device.default_output_config()?;
+        if config.sample_format() != SampleFormat::F32 {
+            bail!(
+                "audio device wants {} samples, only f32 is supported",
+                config.sample_format()
+            );
+        }
+        let format = Format {
+            rate: config.sample_rate().0,
+            channels: config.channels(),
+        };
+        let frames = (format.rate as f32 * latency) as usize;
+        let ring = Arc::new(Mutex::new(RingBuffer::new(frames, format.channels)));
+        let stream = device.build_output_stream(
+            &config.into(),
+            {
+                let ring = ring.clone();
+                move |out: &mut [f32], _| match ring.lock() {
+                    Ok(mut ring) => _ = ring.pop(out),
+                    Err(_) => out.fill(0.0),
+                }
+            },
+            |e| warn!("audio stream error: {e}"),
+            None,
+        )?;
+        stream.play()?;
+        Ok(Self {
+            format,
+            ring,
+            _stream: stream,
+        })
+    }
+
+    pub fn stats(&self) -> Stats {
+        self.ring.lock().map(|r| r.stats()).unwrap_or_default()
+    }
+}
+
+impl std::fmt::Debug for DeviceSink {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DeviceSink")
+            .field("format", &self.format)
+            .field("stats", &self.stats())
+            .finish()
+    }
+}
+
+impl AudioSink for DeviceSink {
+    fn format(&self) -> Format {
+        self.format
+    }
+
+    fn push_f32(&mut self, samples: &[f32]) -> Result<()> {
+        let mut ring = self
+            .ring
+            .lock()
+            .map_err(|_| eyre!("audio callback panicked"))?;
+        ring.push(samples);
+        Ok(())
+    }
+}
@@ -0,0 +1,124 @@
+use std::f32::consts::TAU;
+
+/// a first order filter, as the rc networks on the consoles' audio outputs
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Filter {
+    LowPass { a: f32, y: f32 },
+    HighPass { a: f32, x: f32, y: f32 },
+}
+
+impl Filter {
+    /// passes below `cutoff` hz, for samples at `rate` hz
+    pub fn low_pass(cutoff: f32, rate: u32) -> Self {
+        Filter::LowPass {
+            a: pole(cutoff, rate),
+            y: 0.0,
+        }
+    }
+
+    /// passes above `cutoff` hz, for samples at `rate` hz
+    pub fn high_pass(cutoff: f32, rate: u32) -> Self {
+        Filter::HighPass {
+            a: pole(cutoff, rate),
+            x: 0.0,
+            y: 0.0,
+        }
+    }
+
+    pub fn process(&mut self, input: f32) -> f32 {
+        match self {
+            Filter::LowPass { a, y } => {
+                *y += (1.0 - *a) * (input - *y);
+                *y
+            }
+            // scaled to pass nyquist untouched, as the analog filter passes
+            // the top of the band
+            Filter::HighPass { a, x, y } => {
+                *y = *a * *y + (1.0 + *a) / 2.0 * (input - *x);
+                *x = input;
+                *y
+            }
+        }
+    }
+}
+
+/// how much of the last output a one pole filter keeps each sample
+fn pole(cutoff: f32, rate: u32) -> f32 {
+    (-TAU * cutoff / rate as f32).exp()
+}
+
+/// FilterChain
+///
+/// filters run one after the other, the output stage of a console.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FilterChain(pub Vec<Filter>);
+
+impl FilterChain {
+    /// the NES: high passes at 90hz and 440hz, then a low pass at 14khz
+    pub fn nes(rate: u32) -> Self {
+        Self(vec![
+            Filter::high_pass(90.0, rate),
+            Filter::high_pass(440.0, rate),
+            Filter::low_pass(14_000.0, rate),
+        ])
+    }
+
+    /// the DMG's output capacitor, charging by 0.999958 a clock at
+    /// 4.19mhz, works out to a high pass at about 28hz
+    pub fn game_boy(rate: u32) -> Self {
+        Self(vec![Filter::high_pass(28.0, rate)])
+    }
+
+    pub fn process(&mut self, input: f32) -> f32 {
+        self.0.iter_mut().fold(input, |s, f| f.process(s))
+    }
+
+    pub fn process_slice(&mut self, samples: &mut [f32]) {
+        for s in samples {
+            *s = self.process(*s);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f64::consts::TAU;
+
+    use super::*;
+    use crate::spectrum::amplitude;
+
+    const RATE: u32 = 48_000;
+
+    /// amplitude a unit sine at `freq` comes out of `chain` with
+    fn gain(chain: &FilterChain, freq: f64) -> f32 {
+        let mut chain = chain.clone();
+        let mut out: Vec<f32> = (0..RATE)
+            .map(|i| (TAU * freq * i as f64 / RATE as f64).sin() as f32)
+            .collect();
+        chain.process_slice(&mut out);
+        // skip the first half second the filters settle in
+        amplitude(&out[RATE as usize / 2..], RATE, freq)
+    }
+
+    #[test]
+    fn cutoffs_are_where_they_say() {
+        let low = FilterChain(vec![Filter::low_pass(1000.0, RATE)]);
+        let high = FilterChain(vec![Filter::high_pass(1000.0, RATE)]);
+        for chain in [&low, &high] {
+            let g = gain(chain, 1000.0);
+            assert!((g - 0.707).abs() < 0.03, "{chain:?} {g}");
+        }
+        assert!(gain(&low, 100.0) > 0.99 && gain(&low, 10_000.0) < 0.11);
+        assert!(gain(&high, 10_000.0) > 0.98 && gain(&high, 100.0) < 0.11);
+    }
+
+    #[test]
+    fn console_outputs_block_dc() {
+        for mut chain in [FilterChain::nes(RATE), FilterChain::game_boy(RATE)] {
+            let settled = (0..RATE).map(|_| chain.process(0.5)).last().unwrap();
+            assert!(settled.abs() < 1e-3, "{chain:?} {settled}");
+        }
+        let nes = FilterChain::nes(RATE);
+        assert!(gain(&nes, 2000.0) > 0.9 && gain(&nes, 20.0) < 0.01);
+    }
+}
@@ -1,5 +1,103 @@
-pub fn add(left: usize, right: usize) -> usize {
-    left + right
+//! sound output shared by the emulator cores
+//!
+//! a core pushes interleaved samples into an `AudioSink`. the sink may be a
+//! wav file, nothing at all, or with the `device` feature the sound card.
+//! cores run at their own rate, a `Resampled` sink converts to the rate of
+//! the one it wraps.
+//!
+//! sound chips with several voices build their output with a `Mixer`, which
+//! band-limits each voice's steps with a `BlipBuffer` and runs the result
+//! through the console's output `FilterChain`.
+
+pub mod blip;
+#[cfg(feature = "device")]
+pub mod device;
+pub mod filter;
+pub mod memory;
+pub mod mixer;
+pub mod resample;
+pub mod ring;
+pub mod spectrum;
+pub mod wav;
+
+use std::fmt::Debug;
+
+use color_eyre::Result;
+
+pub use blip::BlipBuffer;
+pub use filter::{Filter, FilterChain};
+pub use memory::{CaptureSink, NullSink};
+pub use mixer::{Mixer, Voice};
+pub use resample::{Resampled, Resampler};
+pub use ring::{RingBuffer, Stats};
+pub use wav::WavSink;
+
+/// sample rate and channel count of a stream
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Format {
+    /// frames per second
+    pub rate: u32,
+    pub channels: u16,
+}
+
+impl Format {
+    pub fn mono(rate: u32) -> Self {
+        Self { rate, channels: 1 }
+    }
+
+    pub fn stereo(rate: u32) -> Self {
+        Self { rate, channels: 2 }
+    }
+}
+
+/// full scale i16 to -1.0..1.0
+pub fn i16_to_f32(s: i16) -> f32 {
+    s as f32 / 32768.0
+}
+
+/// -1.0..1.0 to i16, clipping anything louder
+pub fn f32_to_i16(s: f32) -> i16 {
+    (s * 32768.0)
+        .round()
+        .clamp(i16::MIN as f32, i16::MAX as f32) as i16
+}
+
+/// AudioSink
+///
+/// where a core's sound goes. samples are interleaved, one per channel
+/// for each frame, at the rate of `format`.
+pub trait AudioSink: Debug {
+    fn format(&self) -> Format;
+
+    fn push_f32(&mut self, samples: &[f32]) -> Result<()>;
+
+    fn push_i16(&mut self, samples: &[i16]) -> Result<()> {
+        let samples: Vec<_> = samples.iter().map(|&s| i16_to_f32(s)).collect();
+        self.push_f32(&samples)
+    }
+
+    /// write out anything buffered
+    fn flush(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl<S: AudioSink + ?Sized> AudioSink for Box<S> {
+    fn format(&self) -> Format {
+        (**self).format()
+    }
+
+    fn push_f32(&mut self, samples: &[f32]) -> Result<()> {
+        (**self).push_f32(samples)
+    }
+
+    fn push_i16(&mut self, samples: &[i16]) -> Result<()> {
+        (**self).push_i16(samples)
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        (**self).flush()
+    }
 }
 
 #[cfg(test)]
@@ -7,8 +105,12 @@ mod tests {
     use super::*;
 
     #[test]
-    fn it_works() {
-        let result = add(2, 2);
-        assert_eq!(result, 4);
+    fn sample_conversions() {
+        assert_eq!(i16_to_f32(i16::MIN), -1.0);
+        assert_eq!(f32_to_i16(1.5), i16::MAX);
+        assert_eq!(f32_to_i16(-1.0), i16::MIN);
+        for s in [i16::MIN, -12345, -1, 0, 1, 20000, i16::MAX] {
+            assert_eq!(f32_to_i16(i16_to_f32(s)), s);
+        }
     }
 }
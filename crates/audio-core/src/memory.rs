@@ -0,0 +1,70 @@
+//! sinks that never reach a speaker
+
+use color_eyre::Result;
+
+use crate::{AudioSink, Format};
+
+/// NullSink
+///
+/// throws the sound away, counting the frames, for running a core muted.
+#[derive(Debug, Clone)]
+pub struct NullSink {
+    format: Format,
+    frames: u64,
+}
+
+impl NullSink {
+    pub fn new(format: Format) -> Self {
+        Self { format, frames: 0 }
+    }
+
+    /// frames pushed so far
+    pub fn frames(&self) -> u64 {
+        self.frames
+    }
+}
+
+impl AudioSink for NullSink {
+    fn format(&self) -> Format {
+        self.format
+    }
+
+    fn push_f32(&mut self, samples: &[f32]) -> Result<()> {
+        self.frames += (samples.len() / self.format.channels.max(1) as usize) as u64;
+        Ok(())
+    }
+
+    fn push_i16(&mut self, samples: &[i16]) -> Result<()> {
+        self.frames += (samples.len() / self.format.channels.max(1) as usize) as u64;
+        Ok(())
+    }
+}
+
+/// CaptureSink
+///
+/// keeps every sample pushed, for tests to look at.
+#[derive(Debug, Clone)]
+pub struct CaptureSink {
+    format: Format,
+    pub samples: Vec<f32>,
+}
+
+impl CaptureSink {
+    pub fn new(format: Format) -> Self {
+        Self {
+            format,
+            samples: vec![],
+        }
+    }
+}
+
+impl AudioSink for CaptureSink {
+    fn format(&self) -> Format {
+        self.format
+    }
+
+    fn push_f32(&mut self, samples: &[f32]) -> Result<()> {
+        self.samples.extend_from_slice(samples);
+        Ok(())
+    }
+}
@@ -0,0 +1,232 @@
+use color_eyre::Result;
+
+use crate::{AudioSink, BlipBuffer, FilterChain};
+
+/// a channel of a sound chip, a pulse or noise generator say
+#[derive(Debug, Clone, PartialEq)]
+pub struct Voice {
+    pub name: String,
+    /// 0.0 silent to 1.0
+    pub volume: f32,
+    /// -1.0 hard left, 0.0 both sides at full volume, 1.0 hard right
+    pub pan: f32,
+    pub muted: bool,
+    /// only soloed voices are heard while any is
+    pub solo: bool,
+    /// the voice's own output level
+    level: f32,
+    /// left and right gain of the settings in effect, updated at frame ends
+    gains: [f32; 2],
+    /// what it adds to the left and right side
+    out: [f32; 2],
+}
+
+/// Mixer
+///
+/// sums the voices of a sound chip into band-limited stereo. voices report
+/// their level whenever it changes, timed in the chip's clock, and the
+/// mixer steps each side of the output by the difference.
+///
+/// volume, pan, mute and solo changes are heard from the end of the frame
+/// they were made in.
+#[derive(Debug, Clone)]
+pub struct Mixer {
+    voices: Vec<Voice>,
+    sides: [BlipBuffer; 2],
+    filters: [FilterChain; 2],
+    /// a voice's volume, pan, mute or solo changed this frame
+    dirty: bool,
+    buf: [Vec<f32>; 2],
+}
+
+impl Mixer {
+    /// voices timed in a `clock_rate` hz clock, mixed at `sample_rate` hz
+    pub fn new(clock_rate: f64, sample_rate: u32) -> Self {
+        let blip = BlipBuffer::new(clock_rate, sample_rate);
+        Self {
+            voices: vec![],
+            sides: [blip.clone(), blip],
+            filters: Default::default(),
+            dirty: false,
+            buf: Default::default(),
+        }
+    }
+
+    /// run both sides through `filters` on the way out
+    pub fn set_filters(&mut self, filters: FilterChain) {
+        self.filters = [filters.clone(), filters];
+    }
+
+    /// add a silent, centred voice at full volume, returns its index
+    pub fn add_voice(&mut self, name: impl Into<String>) -> usize {
+        self.voices.push(Voice {
+            name: name.into(),
+            volume: 1.0,
+            pan: 0.0,
+            muted: false,
+            solo: false,
+            level: 0.0,
+            gains: [1.0; 2],
+            out: [0.0; 2],
+        });
+        self.voices.len() - 1
+    }
+
+    pub fn voices(&self) -> &[Voice] {
+        &self.voices
+    }
+
+    /// voice `v` for changing its volume, pan, mute or solo, heard from
+    /// the next `end_frame`
+    pub fn voice_mut(&mut self, v: usize) -> &mut Voice {
+        self.dirty = true;
+        &mut self.voices[v]
+    }
+
+    /// voice `v`'s output is `level` from `time` clocks into the frame
+    pub fn set_level(&mut self, v: usize, time: u32, level: f32) {
+        self.voices[v].level = level;
+        self.update(v, time);
+    }
+
+    /// step the sides to voice `v`'s level at its gains in effect
+    fn update(&mut self, v: usize, time: u32) {
+        let voice = &mut self.voices[v];
+        for (i, gain) in voice.gains.into_iter().enumerate() {
+            let out = voice.level * gain;
+            if out != voice.out[i] {
+                self.sides[i].add_delta(time, out - voice.out[i]);
+                voice.out[i] = out;
+            }
+        }
+    }
+
+    /// the frame ran for `duration` clocks
+    pub fn end_frame(&mut self, duration: u32) {
+        if std::mem::take(&mut self.dirty) {
+            let solo = self.voices.iter().any(|v| v.solo);
+            for v in 0..self.voices.len() {
+                let voice = &mut self.voices[v];
+                let heard = !voice.muted && (voice.solo || !solo);
+                let gain = match heard {
+                    true => voice.volume,
+                    false => 0.0,
+                };
+                voice.gains = [
+                    gain * (1.0 - voice.pan).min(1.0),
+                    gain * (1.0 + voice.pan).min(1.0),
+                ];
+                self.update(v, duration);
+            }
+        }
+        for side in &mut self.sides {
+            side.end_frame(duration);
+        }
+    }
+
+    /// stereo frames ready to read
+    pub fn samples_avail(&self) -> usize {
+        self.sides[0].samples_avail()
+    }
+
+    /// append the frames ready, interleaved left and right, to `out`
+    pub fn read(&mut self, out: &mut Vec<f32>) -> usize {
+        let n = self.samples_avail();
+        for ((side, buf), filters) in self
+            .sides
+            .iter_mut()
+            .zip(&mut self.buf)
+            .zip(&mut self.filters)
+        {
+            buf.resize(n, 0.0);
+            side.read_samples(buf);
+            filters.process_slice(buf);
+        }
+        out.extend(
+            self.buf[0]
+                .iter()
+                .zip(&self.buf[1])
+                .flat_map(|(&l, &r)| [l, r]),
+        );
+        n
+    }
+
+    /// push the frames ready into a stereo `sink`
+    pub fn drain_into(&mut self, sink: &mut impl AudioSink) -> Result<()> {
+        let mut out = vec![];
+        self.read(&mut out);
+        sink.push_f32(&out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::spectrum::amplitude;
+
+    const CLOCK: f64 = 1_000_000.0;
+    const RATE: u32 = 50_000;
+
+    /// a 1khz and a 2.5khz square wave, 1/10s at a time
+    fn play(mixer: &mut Mixer, voices: [usize; 2]) -> (Vec<f32>, Vec<f32>) {
+        for (v, half_period) in voices.into_iter().zip([500, 200]) {
+            for (i, t) in (0..100_000).step_by(half_period).enumerate() {
+                mixer.set_level(v, t, [0.5, -0.5][i % 2]);
+            }
+        }
+        mixer.end_frame(100_000);
+        let mut out = vec![];
+        mixer.read(&mut out);
+        out.chunks(2).map(|f| (f[0], f[1])).unzip()
+    }
+
+    fn tones(samples: &[f32]) -> (bool, bool) {
+        let (a, b) = (
+            amplitude(samples, RATE, 1000.0),
+            amplitude(samples, RATE, 2500.0),
+        );
+        (a > 0.1, b > 0.1)
+    }
+
+    #[test]
+    fn mutes_solos_and_pans() {
+        let mut mixer = Mixer::new(CLOCK, RATE);
+        let voices = [mixer.add_voice("pulse 1"), mixer.add_voice("pulse 2")];
+        let (l, r) = play(&mut mixer, voices);
+        assert_eq!((tones(&l), tones(&r)), ((true, true), (true, true)));
+
+        mixer.voice_mut(voices[0]).muted = true;
+        mixer.end_frame(0);
+        let (l, _) = play(&mut mixer, voices);
+        assert_eq!(tones(&l), (false, true));
+
+        mixer.voice_mut(voices[0]).solo = true;
+        let (l, _) = play(&mut mixer, voices);
+        assert_eq!(tones(&l), (false, true), "held until the frame ends");
+        let (l, _) = play(&mut mixer, voices);
+        assert_eq!(tones(&l), (false, false), "a muted solo is still muted");
+
+        mixer.voice_mut(voices[0]).muted = false;
+        mixer.voice_mut(voices[1]).pan = -1.0;
+        mixer.end_frame(0);
+        let (l, r) = play(&mut mixer, voices);
+        assert_eq!((tones(&l), tones(&r)), ((true, false), (true, false)));
+
+        mixer.voice_mut(voices[0]).solo = false;
+        mixer.end_frame(0);
+        let (l, r) = play(&mut mixer, voices);
+        assert_eq!((tones(&l), tones(&r)), ((true, true), (true, false)));
+    }
+
+    #[test]
+    fn volume_scales_the_harmonics() {
+        let mut mixer = Mixer::new(CLOCK, RATE);
+        let voices = [mixer.add_voice("a"), mixer.add_voice("b")];
+        mixer.voice_mut(voices[1]).volume = 0.5;
+        mixer.end_frame(0);
+        let (l, _) = play(&mut mixer, voices);
+        let a = amplitude(&l, RATE, 1000.0);
+        let b = amplitude(&l, RATE, 2500.0);
+        assert!((a / b - 2.0).abs() < 0.05, "{a} {b}");
+    }
+}
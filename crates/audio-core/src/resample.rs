@@ -0,0 +1,162 @@
+use color_eyre::{eyre::ensure, Result};
+
+use crate::{AudioSink, Format};
+
+/// Resampler
+///
+/// converts an interleaved stream between two rates by linear
+/// interpolation, which is plenty for square wave beepers and keeps no
+/// more than a frame of history. output lags the input by one frame.
+#[derive(Debug, Clone)]
+pub struct Resampler {
+    from: u32,
+    to: u32,
+    channels: usize,
+    /// input frames advanced per output frame
+    step: f64,
+    /// where the next output frame falls, in input frames after `last`
+    pos: f64,
+    /// final frame of the previous input
+    last: Vec<f32>,
+}
+
+impl Resampler {
+    /// fails on a zero rate or channel count, a zero input rate would never
+    /// advance through the input
+    pub fn new(from: u32, to: u32, channels: u16) -> Result<Self> {
+        ensure!(
+            from > 0 && to > 0,
+            "cannot resample from {from} hz to {to} hz"
+        );
+        ensure!(channels > 0, "cannot resample audio with no channels");
+        let channels = channels as usize;
+        Ok(Self {
+            from,
+            to,
+            channels,
+            step: from as f64 / to as f64,
+            pos: 0.0,
+            last: vec![0.0; channels],
+        })
+    }
+
+    /// input and output rate
+    pub fn rates(&self) -> (u32, u32) {
+        (self.from, self.to)
+    }
+
+    /// resample `input`, appending to `out`
+    pub fn process(&mut self, input: &[f32], out: &mut Vec<f32>) {
+        let c = self.channels;
+        let frames = input.len() / c;
+        // frame 0 is `last`, frame k is input frame k - 1
+        let sample = |frame: usize, ch: usize| match frame {
+            0 => self.last[ch],
+            f => input[(f - 1) * c + ch],
+        };
+        while self.pos < frames as f64 {
+            let i = self.pos as usize;
+            let t = (self.pos - i as f64) as f32;
+            for ch in 0..c {
+                let (a, b) = (sample(i, ch), sample(i + 1, ch));
+                out.push(a + (b - a) * t);
+            }
+            self.pos += self.step;
+        }
+        if frames > 0 {
+            self.pos -= frames as f64;
+            self.last
+                .copy_from_slice(&input[(frames - 1) * c..frames * c]);
+        }
+    }
+}
+
+/// Resampled
+///
+/// a sink taking samples at `rate` and passing them on to `sink` at its own.
+#[derive(Debug)]
+pub struct Resampled<S> {
+    sink: S,
+    resampler: Resampler,
+    buf: Vec<f32>,
+}
+
+impl<S: AudioSink> Resampled<S> {
+    pub fn new(sink: S, rate: u32) -> Result<Self> {
+        let Format { rate: to, channels } = sink.format();
+        Ok(Self {
+            resampler: Resampler::new(rate, to, channels)?,
+            sink,
+            buf: vec![],
+        })
+    }
+
+    pub fn inner(&self) -> &S {
+        &self.sink
+    }
+
+    pub fn inner_mut(&mut self) -> &mut S {
+        &mut self.sink
+    }
+
+    pub fn into_inner(self) -> S {
+        self.sink
+    }
+}
+
+impl<S: AudioSink> AudioSink for Resampled<S> {
+    fn format(&self) -> Format {
+        Format {
+            rate: self.resampler.from,
+            channels: self.sink.format().channels,
+        }
+    }
+
+    fn push_f32(&mut self, samples: &[f32]) -> Result<()> {
+        self.buf.clear();
+        self.resampler.process(samples, &mut self.buf);
+        self.sink.push_f32(&self.buf)
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        self.sink.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CaptureSink;
+
+    #[test]
+    fn keeps_the_rate_ratio_across_pushes() {
+        let mut sink = Resampled::new(CaptureSink::new(Format::stereo(48_000)), 32_000).unwrap();
+        assert_eq!(sink.format(), Format::stereo(32_000));
+        // a second of constant stereo sound, pushed in uneven chunks
+        let input = [0.5, -0.25].repeat(32_000);
+        for chunk in input.chunks(2 * 333) {
+            sink.push_f32(chunk).unwrap();
+        }
+        let out = sink.into_inner().samples;
+        assert!(out.len().abs_diff(2 * 48_000) <= 2, "{}", out.len());
+        // after the frame of lag the level comes through untouched
+        assert!(out[4..].chunks(2).all(|f| f == [0.5, -0.25]));
+    }
+
+    #[test]
+    fn interpolates_between_frames() {
+        let mut r = Resampler::new(1, 4, 1).unwrap();
+        let mut out = vec![];
+        r.process(&[1.0], &mut out);
+        r.process(&[0.0], &mut out);
+        assert_eq!(out, [0.0, 0.25, 0.5, 0.75, 1.0, 0.75, 0.5, 0.25]);
+    }
+
+    #[test]
+    fn rejects_zero_rates_and_channels() {
+        assert!(Resampler::new(0, 48_000, 1).is_err());
+        assert!(Resampler::new(48_000, 0, 1).is_err());
+        assert!(Resampler::new(48_000, 44_100, 0).is_err());
+        assert!(Resampled::new(CaptureSink::new(Format::mono(48_000)), 0).is_err());
+    }
+}
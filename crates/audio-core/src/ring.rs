@@ -0,0 +1,118 @@
+use std::collections::VecDeque;
+
+/// what went wrong between the producer and consumer of a `RingBuffer`
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Stats {
+    /// pops that found too few samples
+    pub underruns: u64,
+    /// samples of silence handed out in their place
+    pub silence: u64,
+    /// pushes that did not fit
+    pub overruns: u64,
+    /// samples thrown away by them
+    pub dropped: u64,
+}
+
+/// RingBuffer
+///
+/// a bounded queue of interleaved samples between a core, which produces
+/// them as it runs, and a sound card, which takes them when it needs them.
+/// a late core is covered with silence and a core that runs ahead loses
+/// what does not fit, only whole frames are ever dropped.
+#[derive(Debug, Clone)]
+pub struct RingBuffer {
+    buf: VecDeque<f32>,
+    capacity: usize,
+    channels: usize,
+    stats: Stats,
+}
+
+impl RingBuffer {
+    /// room for `frames` frames of `channels` samples each
+    pub fn new(frames: usize, channels: u16) -> Self {
+        let channels = channels.max(1) as usize;
+        Self {
+            buf: VecDeque::with_capacity(frames * channels),
+            capacity: frames * channels,
+            channels,
+            stats: Stats::default(),
+        }
+    }
+
+    /// queue as many whole frames of `samples` as fit, returns the number
+    /// of samples taken
+    pub fn push(&mut self, samples: &[f32]) -> usize {
+        let room = self.capacity - self.buf.len();
+        let n = samples.len().min(room) / self.channels * self.channels;
+        self.buf.extend(&samples[..n]);
+        if n < samples.len() {
+            self.stats.overruns += 1;
+            self.stats.dropped += (samples.len() - n) as u64;
+        }
+        n
+    }
+
+    /// fill `out` from the front of the queue, and with silence once it is
+    /// empty. returns the number of samples that were queued
+    pub fn pop(&mut self, out: &mut [f32]) -> usize {
+        let n = out.len().min(self.buf.len());
+        for (o, s) in out.iter_mut().zip(self.buf.drain(..n)) {
+            *o = s;
+        }
+        out[n..].fill(0.0);
+        if n < out.len() {
+            self.stats.underruns += 1;
+            self.stats.silence += (out.len() - n) as u64;
+        }
+        n
+    }
+
+    /// samples queued
+    pub fn len(&self) -> usize {
+        self.buf.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.buf.is_empty()
+    }
+
+    /// samples that fit
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn stats(&self) -> Stats {
+        self.stats
+    }
+
+    pub fn clear(&mut self) {
+        self.buf.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_underruns_and_overruns() {
+        let mut ring = RingBuffer::new(3, 2);
+        assert_eq!(ring.push(&[1.0, 2.0, 3.0, 4.0]), 4);
+        // one sample short of a third frame, the whole frame is dropped
+        assert_eq!(ring.push(&[5.0, 6.0, 7.0]), 2);
+        assert_eq!(ring.len(), 6);
+
+        let mut out = [9.0; 8];
+        assert_eq!(ring.pop(&mut out), 6);
+        assert_eq!(out, [1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 0.0, 0.0]);
+        assert_eq!(
+            ring.stats(),
+            Stats {
+                underruns: 1,
+                silence: 2,
+                overruns: 1,
+                dropped: 1,
+            }
+        );
+    }
+}
@@ -0,0 +1,33 @@
+//! frequency content of a signal, for testing what a core sounds like
+
+use std::f64::consts::TAU;
+
+/// amplitude of the `freq` hz component of `samples`, hann windowed so
+/// frequencies that are not a whole number of cycles long leak little.
+/// negative frequencies are read as positive.
+pub fn amplitude(samples: &[f32], rate: u32, freq: f64) -> f32 {
+    let n = samples.len() as f64;
+    let w = TAU * freq.abs() / rate as f64;
+    let (mut re, mut im, mut gain) = (0.0, 0.0, 0.0);
+    for (i, &s) in samples.iter().enumerate() {
+        let hann = 0.5 - 0.5 * (TAU * i as f64 / n).cos();
+        re += hann * s as f64 * (w * i as f64).cos();
+        im -= hann * s as f64 * (w * i as f64).sin();
+        gain += hann;
+    }
+    (2.0 * (re * re + im * im).sqrt() / gain) as f32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn measures_a_sine() {
+        let sine: Vec<f32> = (0..4800)
+            .map(|i| 0.3 * (TAU * 1234.5 * i as f64 / 48_000.0).sin() as f32)
+            .collect();
+        assert!((amplitude(&sine, 48_000, 1234.5) - 0.3).abs() < 1e-3);
+        assert!(amplitude(&sine, 48_000, 2000.0) < 1e-3);
+    }
+}
@@ -0,0 +1,96 @@
+use std::{fmt, fs::File, io::BufWriter, path::Path};
+
+use color_eyre::Result;
+use hound::{SampleFormat, WavSpec, WavWriter};
+
+use crate::{f32_to_i16, AudioSink, Format};
+
+/// WavSink
+///
+/// writes 16-bit pcm to a wav file. the header is only complete once the
+/// sink is `finish`ed or dropped.
+pub struct WavSink {
+    format: Format,
+    writer: WavWriter<BufWriter<File>>,
+}
+
+impl WavSink {
+    pub fn create(path: impl AsRef<Path>, format: Format) -> Result<Self> {
+        let spec = WavSpec {
+            channels: format.channels,
+            sample_rate: format.rate,
+            bits_per_sample: 16,
+            sample_format: SampleFormat::Int,
+        };
+        Ok(Self {
+            format,
+            writer: WavWriter::create(path, spec)?,
+        })
+    }
+
+    /// frames written so far
+    pub fn frames(&self) -> u32 {
+        self.writer.duration()
+    }
+
+    /// write the final header, reporting errors that dropping would hide
+    pub fn finish(self) -> Result<()> {
+        self.writer.finalize()?;
+        Ok(())
+    }
+}
+
+impl fmt::Debug for WavSink {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WavSink")
+            .field("format", &self.format)
+            .field("frames", &self.frames())
+            .finish()
+    }
+}
+
+impl AudioSink for WavSink {
+    fn format(&self) -> Format {
+        self.format
+    }
+
+    fn push_f32(&mut self, samples: &[f32]) -> Result<()> {
+        for &s in samples {
+            self.writer.write_sample(f32_to_i16(s))?;
+        }
+        Ok(())
+    }
+
+    fn push_i16(&mut self, samples: &[i16]) -> Result<()> {
+        for &s in samples {
+            self.writer.write_sample(s)?;
+        }
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        self.writer.flush()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn writes_a_readable_file() {
+        let path = std::env::temp_dir().join(format!("audio-core-{}.wav", std::process::id()));
+        let mut sink = WavSink::create(&path, Format::stereo(8000)).unwrap();
+        sink.push_i16(&[1, -1, 300, -300]).unwrap();
+        sink.push_f32(&[0.5, -2.0]).unwrap();
+        assert_eq!(sink.frames(), 3);
+        sink.finish().unwrap();
+
+        let mut reader = hound::WavReader::open(&path).unwrap();
+        assert_eq!(reader.spec().sample_rate, 8000);
+        let samples: Vec<i16> = reader.samples().map(|s| s.unwrap()).collect();
+        assert_eq!(samples, [1, -1, 300, -300, 16384, -32768]);
+        std::fs::remove_file(path).unwrap();
+    }
+}
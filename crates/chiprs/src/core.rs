@@ -1,7 +1,17 @@
+pub mod asm;
+pub mod beeper;
+pub mod clock;
+pub mod debugger;
+pub mod disassembler;
 pub mod framebuffer;
 pub mod instructions;
+pub mod movie;
 pub mod processor;
+pub mod quirks;
+pub mod rewind;
+pub mod rng;
 pub mod rom;
+pub mod state;
 
 pub use instructions::Instruction;
 pub use processor::Processor;
@@ -0,0 +1,472 @@
+use std::{
+    collections::HashMap,
+    fmt::Write,
+    path::{Path, PathBuf},
+};
+
+use color_eyre::{
+    eyre::{bail, eyre, WrapErr},
+    Result,
+};
+
+use super::{instructions::Instruction, rom::Rom};
+
+/// address roms are loaded at, labels resolve relative to it
+pub const ORIGIN: u16 = 0x200;
+/// deepest chain of `include`s followed before assuming a cycle
+const MAX_INCLUDE_DEPTH: usize = 16;
+/// deepest chain of constants referring to constants
+const MAX_EXPR_DEPTH: usize = 32;
+
+/// assemble the source file at `path`, includes resolve relative to it
+pub fn assemble_file(path: impl AsRef<Path>) -> Result<Rom> {
+    let mut asm = Assembler::default();
+    asm.include(path.as_ref(), 0)?;
+    asm.finish()
+}
+
+/// assemble `src`, includes resolve relative to the working directory
+///
+/// syntax is one statement per line, `;` starts a comment:
+/// - `name:` defines a label at the current address
+/// - `NAME = expr` (or `NAME equ expr`) defines a constant
+/// - `db expr, ..` and `dw expr, ..` emit bytes and big endian words
+/// - `include "path"` assembles another file in place
+/// - anything else is an instruction, e.g. `LD V0, 0x10` or `DRW V0, V1, 5`
+///
+/// expressions are numbers (`10`, `0x0A`, `$0A`, `0b1010`), labels and
+/// constants joined with `+` and `-`.
+pub fn assemble(src: &str) -> Result<Rom> {
+    let mut asm = Assembler::default();
+    asm.source(src, Path::new("<input>"), Path::new("."), 0)?;
+    asm.finish()
+}
+
+/// linear listing of `rom` as assembler source, one statement per word
+///
+/// words that do not decode become `dw`, a trailing odd byte a `db`.
+pub fn disassemble(rom: &Rom) -> String {
+    let bytes = rom.bytes();
+    let mut out = String::new();
+    let mut i = 0;
+    while i + 1 < bytes.len() {
+        let w = u16::from_be_bytes([bytes[i], bytes[i + 1]]);
+        match Instruction::decode(w) {
+            Ok(Instruction::LDILong) if i + 3 < bytes.len() => {
+                let addr = u16::from_be_bytes([bytes[i + 2], bytes[i + 3]]);
+                _ = writeln!(out, "    LD I, LONG 0x{addr:04X}");
+                i += 4;
+                continue;
+            }
+            Ok(Instruction::LDILong) | Err(_) => _ = writeln!(out, "    dw 0x{w:04X}"),
+            Ok(inst) => _ = writeln!(out, "    {}", inst.asm()),
+        }
+        i += 2;
+    }
+    if i < bytes.len() {
+        _ = writeln!(out, "    db 0x{:02X}", bytes[i]);
+    }
+    out
+}
+
+/// where a statement came from, for error messages
+#[derive(Debug, Clone)]
+struct Loc {
+    file: PathBuf,
+    line: usize,
+}
+
+impl std::fmt::Display for Loc {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.file.display(), self.line)
+    }
+}
+
+#[derive(Debug)]
+enum Stmt {
+    Op(String, Vec<String>),
+    Db(Vec<String>),
+    Dw(Vec<String>),
+}
+
+impl Stmt {
+    /// bytes the statement assembles to
+    fn size(&self) -> usize {
+        match self {
+            Stmt::Op(m, ops) if m == "LD" && ops.len() == 2 && long_operand(&ops[1]).is_some() => 4,
+            Stmt::Op(..) => 2,
+            Stmt::Db(v) => v.len(),
+            Stmt::Dw(v) => 2 * v.len(),
+        }
+    }
+}
+
+#[derive(Debug)]
+enum Symbol {
+    Label(u16),
+    Const(String, Loc),
+}
+
+#[derive(Debug, PartialEq)]
+enum Operand {
+    V(u8),
+    I,
+    IndirectI,
+    DT,
+    ST,
+    K,
+    F,
+    HF,
+    B,
+    R,
+    Long(String),
+    Expr(String),
+}
+
+fn long_operand(s: &str) -> Option<&str> {
+    let (head, rest) = s.split_once(char::is_whitespace)?;
+    head.eq_ignore_ascii_case("LONG").then(|| rest.trim())
+}
+
+fn operand(s: &str) -> Operand {
+    if let Some(rest) = long_operand(s) {
+        return Operand::Long(rest.into());
+    }
+    match s.to_ascii_uppercase().as_str() {
+        "I" => Operand::I,
+        "[I]" => Operand::IndirectI,
+        "DT" => Operand::DT,
+        "ST" => Operand::ST,
+        "K" => Operand::K,
+        "F" => Operand::F,
+        "HF" => Operand::HF,
+        "B" => Operand::B,
+        "R" => Operand::R,
+        u if u.len() == 2 && u.starts_with('V') => match u8::from_str_radix(&u[1..], 16) {
+            Ok(x) => Operand::V(x),
+            Err(_) => Operand::Expr(s.into()),
+        },
+        _ => Operand::Expr(s.into()),
+    }
+}
+
+fn is_ident(s: &str) -> bool {
+    let mut c = s.chars();
+    c.next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_' || c == '.')
+        && c.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.')
+}
+
+fn split_operands(s: &str) -> Vec<String> {
+    if s.trim().is_empty() {
+        return vec![];
+    }
+    s.split(',').map(|o| o.trim().to_string()).collect()
+}
+
+#[derive(Default)]
+struct Assembler {
+    symbols: HashMap<String, Symbol>,
+    items: Vec<(Loc, usize, Stmt)>,
+    /// offset of the next statement from `ORIGIN`
+    offset: usize,
+}
+
+impl Assembler {
+    fn include(&mut self, path: &Path, depth: usize) -> Result<()> {
+        if depth > MAX_INCLUDE_DEPTH {
+            bail!("includes nested too deep at {}", path.display());
+        }
+        let src = std::fs::read_to_string(path)
+            .wrap_err_with(|| format!("failed to read {}", path.display()))?;
+        let dir = path.parent().unwrap_or(Path::new("."));
+        self.source(&src, path, dir, depth)
+    }
+
+    /// first pass, collects statements and symbols
+    fn source(&mut self, src: &str, file: &Path, dir: &Path, depth: usize) -> Result<()> {
+        for (n, line) in src.lines().enumerate() {
+            let loc = Loc {
+                file: file.to_path_buf(),
+                line: n + 1,
+            };
+            let mut line = line.split(';').next().unwrap_or_default().trim();
+            // leading labels
+            while let Some((label, rest)) = line.split_once(':') {
+                if !is_ident(label.trim()) {
+                    break;
+                }
+                self.define(label.trim(), Symbol::Label(self.address(&loc)?), &loc)?;
+                line = rest.trim();
+            }
+            if line.is_empty() {
+                continue;
+            }
+            let (head, rest) = line
+                .split_once(char::is_whitespace)
+                .map(|(h, r)| (h, r.trim()))
+                .unwrap_or((line, ""));
+            if let Some(value) = rest.strip_prefix('=') {
+                self.define(head, Symbol::Const(value.trim().into(), loc.clone()), &loc)?;
+                continue;
+            }
+            if let Some((name, value)) = rest.split_once(char::is_whitespace) {
+                if name.eq_ignore_ascii_case("equ") {
+                    self.define(head, Symbol::Const(value.trim().into(), loc.clone()), &loc)?;
+                    continue;
+                }
+            }
+            let stmt = match head.to_ascii_lowercase().as_str() {
+                "include" => {
+                    let path = rest.trim_matches('"');
+                    self.include(&dir.join(path), depth + 1)
+                        .wrap_err_with(|| format!("{loc}: in include"))?;
+                    continue;
+                }
+                "db" => Stmt::Db(split_operands(rest)),
+                "dw" => Stmt::Dw(split_operands(rest)),
+                _ => Stmt::Op(head.to_ascii_uppercase(), split_operands(rest)),
+            };
+            let size = stmt.size();
+            self.items.push((loc, self.offset, stmt));
+            self.offset += size;
+        }
+        Ok(())
+    }
+
+    fn address(&self, loc: &Loc) -> Result<u16> {
+        u16::try_from(ORIGIN as usize + self.offset).map_err(|_| eyre!("{loc}: rom too large"))
+    }
+
+    fn define(&mut self, name: &str, symbol: Symbol, loc: &Loc) -> Result<()> {
+        if !is_ident(name) {
+            bail!("{loc}: invalid symbol name `{name}`");
+        }
+        if self.symbols.insert(name.into(), symbol).is_some() {
+            bail!("{loc}: `{name}` defined twice");
+        }
+        Ok(())
+    }
+
+    /// second pass, resolves symbols and writes the rom
+    fn finish(self) -> Result<Rom> {
+        let mut rom = Rom::with_len(self.offset);
+        for (loc, offset, stmt) in &self.items {
+            self.emit(&mut rom, *offset, stmt)
+                .wrap_err_with(|| format!("{loc}"))?;
+        }
+        Ok(rom)
+    }
+
+    fn emit(&self, rom: &mut Rom, offset: usize, stmt: &Stmt) -> Result<()> {
+        match stmt {
+            Stmt::Db(v) => {
+                for (i, e) in v.iter().enumerate() {
+                    let b = self.value(e, -0x80, 0xFF)?;
+                    rom.write(offset + i, &[b as u8]);
+                }
+            }
+            Stmt::Dw(v) => {
+                for (i, e) in v.iter().enumerate() {
+                    let w = self.value(e, -0x8000, 0xFFFF)?;
+                    rom.write(offset + 2 * i, &(w as u16).to_be_bytes());
+                }
+            }
+            Stmt::Op(m, ops) => {
+                let ops: Vec<Operand> = ops.iter().map(|o| operand(o)).collect();
+                let (inst, long) = self.instruction(m, &ops)?;
+                rom.insert(offset, inst);
+                if let Some(addr) = long {
+                    rom.write(offset + 2, &addr.to_be_bytes());
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn instruction(&self, m: &str, ops: &[Operand]) -> Result<(Instruction, Option<u16>)> {
+        use Operand::*;
+        let addr = |e: &str| self.value(e, 0, 0xFFF).map(|v| v as u16);
+        let byte = |e: &str| self.value(e, -0x80, 0xFF).map(|v| v as u8);
+        let nibble = |e: &str| self.value(e, 0, 0xF).map(|v| v as u8);
+        let inst = match (m, ops) {
+            ("CLS", []) => Instruction::CLS,
+            ("RET", []) => Instruction::RET,
+            ("SYS", [Expr(a)]) => Instruction::SYS(addr(a)?),
+            ("SCD", [Expr(n)]) => Instruction::SCDn(nibble(n)?),
+            ("SCU", [Expr(n)]) => Instruction::SCUn(nibble(n)?),
+            ("SCR", []) => Instruction::SCR,
+            ("SCL", []) => Instruction::SCL,
+            ("EXIT", []) => Instruction::EXIT,
+            ("LOW", []) => Instruction::LOW,
+            ("HIGH", []) => Instruction::HIGH,
+            ("JP", [Expr(a)]) => Instruction::JPAddr(addr(a)?),
+            ("JP", [V(0), Expr(a)]) => Instruction::JPV0Addr(addr(a)?),
+            ("CALL", [Expr(a)]) => Instruction::CALLAddr(addr(a)?),
+            ("SE", [V(x), V(y)]) => Instruction::SExy(*x, *y),
+            ("SE", [V(x), Expr(b)]) => Instruction::SExByte(*x, byte(b)?),
+            ("SNE", [V(x), V(y)]) => Instruction::SNExy(*x, *y),
+            ("SNE", [V(x), Expr(b)]) => Instruction::SNExByte(*x, byte(b)?),
+            ("SAVE", [V(x), V(y)]) => Instruction::SAVExy(*x, *y),
+            ("LOAD", [V(x), V(y)]) => Instruction::LOADxy(*x, *y),
+            ("LD", [V(x), V(y)]) => Instruction::LDxy(*x, *y),
+            ("LD", [V(x), Expr(b)]) => Instruction::LDxByte(*x, byte(b)?),
+            ("LD", [I, Expr(a)]) => Instruction::LDIAddr(addr(a)?),
+            ("LD", [I, Long(a)]) => {
+                let a = self.value(a, 0, 0xFFFF)? as u16;
+                return Ok((Instruction::LDILong, Some(a)));
+            }
+            ("LD", [V(x), DT]) => Instruction::LDxDt(*x),
+            ("LD", [V(x), K]) => Instruction::LDxK(*x),
+            ("LD", [DT, V(x)]) => Instruction::LDDTx(*x),
+            ("LD", [ST, V(x)]) => Instruction::LDSTx(*x),
+            ("LD", [F, V(x)]) => Instruction::LDFx(*x),
+            ("LD", [HF, V(x)]) => Instruction::LDHFx(*x),
+            ("LD", [B, V(x)]) => Instruction::LDBx(*x),
+            ("LD", [IndirectI, V(x)]) => Instruction::LDIx(*x),
+            ("LD", [V(x), IndirectI]) => Instruction::LDxI(*x),
+            ("LD", [R, V(x)]) => Instruction::LDRx(*x),
+            ("LD", [V(x), R]) => Instruction::LDxR(*x),
+            ("ADD", [V(x), V(y)]) => Instruction::ADDxy(*x, *y),
+            ("ADD", [V(x), Expr(b)]) => Instruction::ADDxByte(*x, byte(b)?),
+            ("ADD", [I, V(x)]) => Instruction::ADDIx(*x),
+            ("OR", [V(x), V(y)]) => Instruction::ORxy(*x, *y),
+            ("AND", [V(x), V(y)]) => Instruction::ANDxy(*x, *y),
+            ("XOR", [V(x), V(y)]) => Instruction::XORxy(*x, *y),
+            ("SUB", [V(x), V(y)]) => Instruction::SUBxy(*x, *y),
+            ("SUBN", [V(x), V(y)]) => Instruction::SUBNxy(*x, *y),
+            ("SHR", [V(x)]) => Instruction::SHRxy(*x, *x),
+            ("SHR", [V(x), V(y)]) => Instruction::SHRxy(*x, *y),
+            ("SHL", [V(x)]) => Instruction::SHLxy(*x, *x),
+            ("SHL", [V(x), V(y)]) => Instruction::SHLxy(*x, *y),
+            ("RND", [V(x), Expr(b)]) => Instruction::RNDxByte(*x, byte(b)?),
+            ("DRW", [V(x), V(y), Expr(n)]) => Instruction::DRWxyn(*x, *y, nibble(n)?),
+            ("SKP", [V(x)]) => Instruction::SKPx(*x),
+            ("SKNP", [V(x)]) => Instruction::SKPNPx(*x),
+            ("PLANE", [Expr(n)]) => Instruction::PLANEn(nibble(n)?),
+            ("AUDIO", []) => Instruction::AUDIO,
+            ("PITCH", [V(x)]) => Instruction::PITCHx(*x),
+            _ => bail!("invalid instruction `{m}` with operands {ops:?}"),
+        };
+        Ok((inst, None))
+    }
+
+    /// evaluate `expr`, checking it lies within `min..=max`
+    fn value(&self, expr: &str, min: i64, max: i64) -> Result<i64> {
+        let v = self.eval(expr, 0)?;
+        if v < min || v > max {
+            bail!("`{expr}` = {v} out of range {min}..={max}");
+        }
+        Ok(v)
+    }
+
+    fn eval(&self, expr: &str, depth: usize) -> Result<i64> {
+        if depth > MAX_EXPR_DEPTH {
+            bail!("constants nested too deep evaluating `{expr}`");
+        }
+        let mut total = 0i64;
+        let mut sign = 1;
+        let mut term = String::new();
+        for c in expr.chars().chain(std::iter::once('+')) {
+            match c {
+                '+' | '-' if !term.trim().is_empty() => {
+                    total += sign * self.term(term.trim(), depth)?;
+                    term.clear();
+                    sign = if c == '-' { -1 } else { 1 };
+                }
+                '-' => sign = -sign,
+                '+' => {}
+                c => term.push(c),
+            }
+        }
+        Ok(total)
+    }
+
+    fn term(&self, t: &str, depth: usize) -> Result<i64> {
+        let parsed = if let Some(h) = t.strip_prefix("0x").or_else(|| t.strip_prefix('$')) {
+            i64::from_str_radix(h, 16)
+        } else if let Some(b) = t.strip_prefix("0b") {
+            i64::from_str_radix(b, 2)
+        } else {
+            t.parse()
+        };
+        if let Ok(v) = parsed {
+            return Ok(v);
+        }
+        match self.symbols.get(t) {
+            Some(Symbol::Label(a)) => Ok(*a as i64),
+            Some(Symbol::Const(e, loc)) => self
+                .eval(e, depth + 1)
+                .wrap_err_with(|| format!("{loc}: in constant `{t}`")),
+            None => bail!("unknown symbol `{t}`"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_word_round_trips() {
+        for w in 0..=0xFFFFu16 {
+            let rom = Rom::from_bytes(w.to_be_bytes().to_vec()).unwrap();
+            let src = disassemble(&rom);
+            let out = assemble(&src).unwrap_or_else(|e| panic!("{w:04X} `{src}`: {e:?}"));
+            assert_eq!(out.bytes(), rom.bytes(), "{w:04X} `{src}`");
+        }
+    }
+
+    #[test]
+    fn long_load_round_trips() {
+        let rom = Rom::from_bytes(vec![0xF0, 0x00, 0x12, 0x34, 0x00, 0xE0]).unwrap();
+        let src = disassemble(&rom);
+        assert!(src.contains("LD I, LONG 0x1234"));
+        assert_eq!(assemble(&src).unwrap().bytes(), rom.bytes());
+    }
+
+    #[test]
+    fn labels_constants_and_data() {
+        let rom = assemble(
+            "
+            X = 4
+            Y equ X + 2 ; constants can refer to each other
+            start:  LD V0, X
+                    ld v1, Y
+                    LD I, sprite
+                    DRW V0, V1, sprite_end - sprite
+            loop:   JP loop
+            sprite: db 0xF0, 0x90, $F0
+            sprite_end:
+                    dw start, 0b1010
+            ",
+        )
+        .unwrap();
+        assert_eq!(
+            rom.bytes(),
+            [
+                0x60, 0x04, 0x61, 0x06, 0xA2, 0x0A, 0xD0, 0x13, 0x12, 0x08, 0xF0, 0x90, 0xF0, 0x02,
+                0x00, 0x00, 0x0A
+            ]
+        );
+    }
+
+    #[test]
+    fn include_is_relative_to_the_including_file() {
+        let dir = std::env::temp_dir().join(format!("chiprs-asm-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("lib")).unwrap();
+        std::fs::write(dir.join("main.s"), "CALL sub\ninclude \"lib/sub.s\"\n").unwrap();
+        std::fs::write(dir.join("lib/sub.s"), "sub: RET\n").unwrap();
+        let rom = assemble_file(dir.join("main.s")).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(rom.bytes(), [0x22, 0x02, 0x00, 0xEE]);
+    }
+
+    #[test]
+    fn errors_point_at_the_line() {
+        let err = assemble("CLS\nLD V0, nowhere\n").unwrap_err();
+        assert!(format!("{err:?}").contains("<input>:2"), "{err:?}");
+        assert!(assemble("LD V0, 0x100").is_err());
+        assert!(assemble("a: CLS\na: RET").is_err());
+    }
+}
@@ -0,0 +1,130 @@
+use std::f32::consts::TAU;
+
+use audio_core::{AudioSink, Format};
+use clap::ValueEnum;
+use color_eyre::Result;
+
+use super::{clock::TIMER_HZ, Processor};
+
+/// shape of the beeper's tone
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Waveform {
+    #[default]
+    Square,
+    Triangle,
+    Sawtooth,
+    Sine,
+}
+
+impl Waveform {
+    /// level at `phase`, 0.0..1.0 through a cycle
+    fn sample(self, phase: f32) -> f32 {
+        match self {
+            Waveform::Square if phase < 0.5 => 1.0,
+            Waveform::Square => -1.0,
+            Waveform::Triangle => 4.0 * (phase - 0.5).abs() - 1.0,
+            Waveform::Sawtooth => 2.0 * phase - 1.0,
+            Waveform::Sine => (phase * TAU).sin(),
+        }
+    }
+}
+
+/// how the beeper sounds
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Tone {
+    /// frequency in hz
+    pub pitch: f32,
+    /// 0.0 is silent, 1.0 full scale
+    pub volume: f32,
+    pub waveform: Waveform,
+}
+
+impl Default for Tone {
+    fn default() -> Self {
+        Self {
+            pitch: 440.0,
+            volume: 0.25,
+            waveform: Waveform::Square,
+        }
+    }
+}
+
+/// Beeper
+///
+/// the chip8 buzzer, a tone for every frame the sound timer runs through
+/// and silence otherwise, written a frame at a time to a sink at its own
+/// rate. XO-CHIP audio patterns are not played, they beep like the rest.
+#[derive(Debug)]
+pub struct Beeper {
+    sink: Box<dyn AudioSink>,
+    tone: Tone,
+    /// position in the current cycle, restarted with every beep
+    phase: f32,
+    /// sample frames owed from previous frames, scaled by `TIMER_HZ`
+    remainder: u64,
+    buf: Vec<f32>,
+}
+
+impl Beeper {
+    pub fn new(sink: Box<dyn AudioSink>, tone: Tone) -> Self {
+        Self {
+            sink,
+            tone,
+            phase: 0.0,
+            remainder: 0,
+            buf: vec![],
+        }
+    }
+
+    pub fn tone(&self) -> Tone {
+        self.tone
+    }
+
+    /// sound of the frame `proc` just ran
+    pub fn frame(&mut self, proc: &Processor) -> Result<()> {
+        let Format { rate, channels } = self.sink.format();
+        self.remainder += rate as u64;
+        let frames = self.remainder / TIMER_HZ;
+        self.remainder %= TIMER_HZ;
+
+        self.buf.clear();
+        if !proc.beeping() {
+            self.phase = 0.0;
+            self.buf.resize(frames as usize * channels as usize, 0.0);
+        } else {
+            let Tone {
+                pitch,
+                volume,
+                waveform,
+            } = self.tone;
+            for _ in 0..frames {
+                let s = waveform.sample(self.phase) * volume;
+                self.buf.extend(std::iter::repeat_n(s, channels as usize));
+                self.phase = (self.phase + pitch / rate as f32).fract();
+            }
+        }
+        self.sink.push_f32(&self.buf)
+    }
+
+    /// write out what the sink has buffered
+    pub fn flush(&mut self) -> Result<()> {
+        self.sink.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn waveforms_span_full_scale() {
+        for wave in Waveform::value_variants() {
+            let levels: Vec<f32> = (0..100).map(|i| wave.sample(i as f32 / 100.0)).collect();
+            let max = levels.iter().copied().fold(f32::MIN, f32::max);
+            let min = levels.iter().copied().fold(f32::MAX, f32::min);
+            assert!(max > 0.95 && min < -0.95, "{wave:?} {min}..{max}");
+        }
+        assert_eq!(Waveform::Triangle.sample(0.25), 0.0);
+        assert_eq!(Waveform::Sawtooth.sample(0.75), 0.5);
+    }
+}
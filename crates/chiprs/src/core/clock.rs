@@ -0,0 +1,130 @@
+use std::time::Duration;
+
+use color_eyre::Result;
+
+use super::Processor;
+
+/// rate the delay and sound timers count down at
+pub const TIMER_HZ: u64 = 60;
+/// instructions per second used when none is configured
+pub const DEFAULT_IPS: u32 = 700;
+/// most wall time a single `advance` will catch up on, so a stalled window
+/// does not fast-forward the game afterwards
+const MAX_CATCHUP: Duration = Duration::from_millis(250);
+const NANOS_PER_SEC: u64 = 1_000_000_000;
+
+/// Clock
+///
+/// drives a `Processor` at a fixed instruction rate while decrementing its
+/// timers at exactly 60hz, either from elapsed wall time or frame by frame
+/// for headless runs.
+#[derive(Debug, Clone)]
+pub struct Clock {
+    ips: u32,
+    /// elapsed time not yet spent on a frame, scaled by `TIMER_HZ`
+    pending: u64,
+    /// instructions owed from previous frames, scaled by `TIMER_HZ`
+    cycle_remainder: u64,
+}
+
+impl Default for Clock {
+    fn default() -> Self {
+        Self::new(DEFAULT_IPS)
+    }
+}
+
+impl Clock {
+    pub fn new(ips: u32) -> Self {
+        Self {
+            ips,
+            pending: 0,
+            cycle_remainder: 0,
+        }
+    }
+
+    pub fn ips(&self) -> u32 {
+        self.ips
+    }
+
+    /// instructions owed from previous frames, in 60ths
+    pub fn cycle_remainder(&self) -> u64 {
+        self.cycle_remainder
+    }
+
+    /// carry on owing `cycle_remainder` 60ths, as after a rewind
+    pub fn set_cycle_remainder(&mut self, cycle_remainder: u64) {
+        self.cycle_remainder = cycle_remainder % TIMER_HZ;
+    }
+
+    /// run every frame that fits in `dt` of wall time, returns frames run
+    pub fn advance(&mut self, proc: &mut Processor, dt: Duration) -> Result<u64> {
+        let frames = self.frames_due(dt);
+        self.run_frames(proc, frames)?;
+        Ok(frames)
+    }
+
+    /// account for `dt` of wall time, returning the whole frames now due
+    pub fn frames_due(&mut self, dt: Duration) -> u64 {
+        let dt = dt.min(MAX_CATCHUP);
+        self.pending += dt.as_nanos() as u64 * TIMER_HZ;
+        let frames = self.pending / NANOS_PER_SEC;
+        self.pending %= NANOS_PER_SEC;
+        frames
+    }
+
+    /// run `n` frames back to back, independent of wall time
+    pub fn run_frames(&mut self, proc: &mut Processor, n: u64) -> Result<()> {
+        for _ in 0..n {
+            self.frame(proc)?;
+        }
+        Ok(())
+    }
+
+    /// run one 1/60s frame: its share of instructions, then one timer tick
+    pub fn frame(&mut self, proc: &mut Processor) -> Result<()> {
+        // carry the fraction so e.g. 700 ips alternates 11 and 12 per frame
+        self.cycle_remainder += self.ips as u64;
+        let cycles = self.cycle_remainder / TIMER_HZ;
+        self.cycle_remainder %= TIMER_HZ;
+        proc.run(cycles as usize)?;
+        proc.tick_timers();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn carries_the_fraction_of_an_op() {
+        // blank memory is all `SYS`, every op moves the PC on 2
+        let mut proc = Processor::default();
+        let mut clock = Clock::new(700);
+        let ops: Vec<u16> = (0..60)
+            .map(|_| {
+                let pc = proc.program_counter();
+                clock.frame(&mut proc).unwrap();
+                (proc.program_counter() - pc) / 2
+            })
+            .collect();
+        assert_eq!(ops[..6], [11, 12, 12, 11, 12, 12]);
+        assert_eq!(ops.iter().sum::<u16>(), 700);
+        assert_eq!(clock.cycle_remainder(), 0);
+    }
+
+    #[test]
+    fn frames_follow_wall_time_up_to_the_catchup() {
+        let mut clock = Clock::default();
+        assert_eq!(clock.frames_due(Duration::from_millis(10)), 0);
+        assert_eq!(clock.frames_due(Duration::from_millis(7)), 1);
+        assert_eq!(clock.frames_due(Duration::from_secs(1)), 15);
+    }
+
+    #[test]
+    fn cycle_remainder_stays_below_a_frame() {
+        let mut clock = Clock::default();
+        clock.set_cycle_remainder(125);
+        assert_eq!(clock.cycle_remainder(), 5);
+    }
+}
@@ -0,0 +1,652 @@
+use std::fmt::{self, Display, Write};
+
+use color_eyre::{
+    eyre::{bail, eyre},
+    Result,
+};
+
+use super::{clock::TIMER_HZ, Instruction, Processor};
+
+/// seconds of emulated time `continue` runs before giving control back
+pub const CONTINUE_SECS: u64 = 60;
+
+/// where execution should stop
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Breakpoint {
+    /// PC reaches the address
+    Addr(u16),
+    /// the op at PC matches `value` on the bits set in `mask`
+    Opcode { value: u16, mask: u16 },
+}
+
+impl Breakpoint {
+    /// opcode pattern of 4 nibbles, any non hex digit matches anything,
+    /// so `8xy4` breaks on every `ADD Vx, Vy`
+    pub fn opcode(pattern: &str) -> Result<Self> {
+        if pattern.chars().count() != 4 {
+            bail!("opcode pattern `{pattern}` is not 4 nibbles");
+        }
+        let (mut value, mut mask) = (0, 0);
+        for c in pattern.chars() {
+            value <<= 4;
+            mask <<= 4;
+            if let Some(d) = c.to_digit(16) {
+                value |= d as u16;
+                mask |= 0xF;
+            }
+        }
+        Ok(Breakpoint::Opcode { value, mask })
+    }
+
+    fn hit(&self, pc: u16, op: Option<u16>) -> bool {
+        match *self {
+            Breakpoint::Addr(a) => a == pc,
+            Breakpoint::Opcode { value, mask } => op.is_some_and(|op| op & mask == value),
+        }
+    }
+}
+
+impl Display for Breakpoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Breakpoint::Addr(a) => write!(f, "pc == 0x{a:03X}"),
+            Breakpoint::Opcode { value, mask } => {
+                f.write_str("op == ")?;
+                for shift in [12, 8, 4, 0] {
+                    match (mask >> shift) & 0xF {
+                        0 => f.write_char('_')?,
+                        _ => write!(f, "{:X}", (value >> shift) & 0xF)?,
+                    }
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// state whose changes stop execution
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Watch {
+    Register(u8),
+    I,
+    /// a byte of memory
+    Memory(u16),
+}
+
+impl Watch {
+    fn read(&self, proc: &Processor) -> u16 {
+        match *self {
+            Watch::Register(x) => proc.registers()[x as usize & 0xF] as u16,
+            Watch::I => proc.i(),
+            Watch::Memory(a) => proc.memory().get(a as usize).copied().unwrap_or(0) as u16,
+        }
+    }
+}
+
+impl Display for Watch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Watch::Register(x) => write!(f, "V{x:X}"),
+            Watch::I => f.write_str("I"),
+            Watch::Memory(a) => write!(f, "[0x{a:03X}]"),
+        }
+    }
+}
+
+/// why execution stopped
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stop {
+    /// the requested steps all ran
+    Done,
+    Breakpoint(Breakpoint),
+    Watch {
+        watch: Watch,
+        old: u16,
+        new: u16,
+    },
+    /// the rom ran `EXIT`
+    Halted,
+    /// a `CONTINUE_SECS` worth of ops ran without anything else stopping them
+    Limit(u64),
+}
+
+impl Display for Stop {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Stop::Done => f.write_str("stepped"),
+            Stop::Breakpoint(b) => write!(f, "breakpoint, {b}"),
+            Stop::Watch { watch, old, new } => {
+                write!(f, "watch, {watch} changed 0x{old:02X} -> 0x{new:02X}")
+            }
+            Stop::Halted => f.write_str("rom exited"),
+            Stop::Limit(ops) => write!(f, "ran {ops} ops without stopping"),
+        }
+    }
+}
+
+/// Debugger
+///
+/// drives a `Processor` one op at a time, stopping on breakpoints and
+/// watched state. timers tick after every frame's share of `ips`, 11 and 12
+/// ops in turn at 700 ips, as under `Clock`.
+#[derive(Debug)]
+pub struct Debugger {
+    proc: Processor,
+    ips: u32,
+    /// ops run since the last timer tick
+    cycles: u64,
+    /// ops owed from previous frames, scaled by `TIMER_HZ` as in `Clock`
+    cycle_remainder: u64,
+    breakpoints: Vec<Breakpoint>,
+    /// watched state with the value it was last seen at
+    watches: Vec<(Watch, u16)>,
+}
+
+impl Debugger {
+    pub fn new(proc: Processor, ips: u32) -> Self {
+        Self {
+            proc,
+            ips,
+            cycles: 0,
+            cycle_remainder: 0,
+            breakpoints: vec![],
+            watches: vec![],
+        }
+    }
+
+    pub fn processor(&self) -> &Processor {
+        &self.proc
+    }
+
+    pub fn processor_mut(&mut self) -> &mut Processor {
+        &mut self.proc
+    }
+
+    pub fn breakpoints(&self) -> &[Breakpoint] {
+        &self.breakpoints
+    }
+
+    pub fn add_breakpoint(&mut self, b: Breakpoint) {
+        self.breakpoints.push(b);
+    }
+
+    /// remove breakpoint `n` as numbered by `breakpoints`
+    pub fn remove_breakpoint(&mut self, n: usize) -> Option<Breakpoint> {
+        (n < self.breakpoints.len()).then(|| self.breakpoints.remove(n))
+    }
+
+    pub fn watches(&self) -> impl Iterator<Item = &Watch> {
+        self.watches.iter().map(|(w, _)| w)
+    }
+
+    pub fn add_watch(&mut self, w: Watch) {
+        let value = w.read(&self.proc);
+        self.watches.push((w, value));
+    }
+
+    pub fn remove_watch(&mut self, n: usize) -> Option<Watch> {
+        (n < self.watches.len()).then(|| self.watches.remove(n).0)
+    }
+
+    /// ops in `CONTINUE_SECS` at the rate the debugger runs at
+    fn continue_limit(&self) -> u64 {
+        self.ips as u64 * CONTINUE_SECS
+    }
+
+    /// the op at PC
+    pub fn opcode(&self) -> Option<u16> {
+        word(self.proc.memory(), self.proc.program_counter())
+    }
+
+    /// run a single op, ticking the timers when a frame's worth have run
+    fn cycle(&mut self) -> Result<Option<Stop>> {
+        if self.proc.halted() {
+            return Ok(Some(Stop::Halted));
+        }
+        self.proc.step()?;
+        self.cycles += 1;
+        let owed = self.cycle_remainder + self.ips as u64;
+        if self.cycles >= owed / TIMER_HZ {
+            self.cycles = 0;
+            self.cycle_remainder = owed % TIMER_HZ;
+            self.proc.tick_timers();
+        }
+        for (watch, seen) in &mut self.watches {
+            let new = watch.read(&self.proc);
+            if new != *seen {
+                let old = std::mem::replace(seen, new);
+                return Ok(Some(Stop::Watch {
+                    watch: *watch,
+                    old,
+                    new,
+                }));
+            }
+        }
+        if self.proc.halted() {
+            return Ok(Some(Stop::Halted));
+        }
+        let (pc, op) = (self.proc.program_counter(), self.opcode());
+        Ok(self
+            .breakpoints
+            .iter()
+            .find(|b| b.hit(pc, op))
+            .map(|b| Stop::Breakpoint(*b)))
+    }
+
+    /// run ops until `done` holds or something stops execution
+    fn run_until(&mut self, limit: u64, done: impl Fn(&Processor) -> bool) -> Result<Stop> {
+        for _ in 0..limit {
+            if let Some(stop) = self.cycle()? {
+                return Ok(stop);
+            }
+            if done(&self.proc) {
+                return Ok(Stop::Done);
+            }
+        }
+        Ok(Stop::Limit(limit))
+    }
+
+    /// run `n` ops
+    pub fn step(&mut self, n: u64) -> Result<Stop> {
+        for _ in 0..n {
+            if let Some(stop) = self.cycle()? {
+                return Ok(stop);
+            }
+        }
+        Ok(Stop::Done)
+    }
+
+    /// run one op, running a `CALL` through to its return
+    pub fn step_over(&mut self) -> Result<Stop> {
+        let is_call = self
+            .opcode()
+            .and_then(|op| Instruction::decode(op).ok())
+            .is_some_and(|i| matches!(i, Instruction::CALLAddr(_)));
+        if !is_call {
+            return self.step(1);
+        }
+        let depth = self.proc.stack_pointer();
+        self.run_until(self.continue_limit(), |p| p.stack_pointer() <= depth)
+    }
+
+    /// run until the current subroutine returns
+    pub fn finish(&mut self) -> Result<Stop> {
+        let depth = self.proc.stack_pointer();
+        if depth == 0 {
+            bail!("not in a subroutine");
+        }
+        self.run_until(self.continue_limit(), |p| p.stack_pointer() < depth)
+    }
+
+    /// run until a breakpoint, watch or `EXIT` stops execution
+    pub fn resume(&mut self) -> Result<Stop> {
+        self.run_until(self.continue_limit(), |_| false)
+    }
+
+    /// V0..VF, I, PC, SP and the timers
+    pub fn registers(&self) -> String {
+        let p = &self.proc;
+        let mut out = (p.registers().iter().enumerate())
+            .map(|(x, v)| format!("V{x:X}={v:02X}"))
+            .collect::<Vec<_>>()
+            .join(" ");
+        _ = write!(
+            out,
+            "\nI=0x{:03X} PC=0x{:03X} SP={} DT={} ST={}",
+            p.i(),
+            p.program_counter(),
+            p.stack_pointer(),
+            p.delay_timer(),
+            p.sound_timer()
+        );
+        out
+    }
+
+    /// hex dump of `len` bytes from `addr`, 16 to a line
+    pub fn memory(&self, addr: u16, len: usize) -> String {
+        let mem = self.proc.memory();
+        let start = (addr as usize).min(mem.len());
+        let end = (start + len).min(mem.len());
+        let mut out = String::new();
+        for (n, line) in mem[start..end].chunks(16).enumerate() {
+            _ = write!(out, "0x{:03X}:", start + n * 16);
+            for b in line {
+                _ = write!(out, " {b:02X}");
+            }
+            out.push('\n');
+        }
+        out
+    }
+
+    /// the call stack, innermost first
+    pub fn stack(&self) -> String {
+        if self.proc.stack().is_empty() {
+            return "empty stack".to_string();
+        }
+        let mut out = String::new();
+        for (n, ret) in self.proc.stack().iter().enumerate().rev() {
+            _ = writeln!(out, "#{n} returns to 0x{ret:03X}");
+        }
+        out
+    }
+
+    /// `n` ops from `addr` as assembler text, PC marked with `>`
+    pub fn disassemble(&self, addr: u16, n: usize) -> String {
+        let mem = self.proc.memory();
+        let mut out = String::new();
+        let mut addr = addr;
+        for _ in 0..n {
+            let Some(op) = word(mem, addr) else {
+                break;
+            };
+            let marker = if addr == self.proc.program_counter() {
+                '>'
+            } else {
+                ' '
+            };
+            let (text, len) = match Instruction::decode(op) {
+                Ok(Instruction::LDILong) => match word(mem, addr + 2) {
+                    Some(a) => (format!("LD I, LONG 0x{a:04X}"), 4),
+                    None => ("LD I, LONG".to_string(), 2),
+                },
+                Ok(inst) => (inst.asm(), 2),
+                Err(_) => (format!("dw 0x{op:04X}"), 2),
+            };
+            _ = writeln!(out, "{marker} 0x{addr:03X}  {op:04X}  {text}");
+            addr = addr.wrapping_add(len);
+        }
+        out
+    }
+
+    /// the display, `#` for lit pixels
+    pub fn screen(&self) -> String {
+        self.proc
+            .framebuffer()
+            .rows()
+            .map(|row| {
+                row.iter()
+                    .map(|p| if *p != 0 { '#' } else { '.' })
+                    .chain(['\n'])
+                    .collect::<String>()
+            })
+            .collect()
+    }
+
+    /// run a debugger command, returning what to print
+    pub fn execute(&mut self, command: DebugCommand) -> Result<String> {
+        let stop = match command {
+            DebugCommand::Step(n) => Some(self.step(n)?),
+            DebugCommand::Next => Some(self.step_over()?),
+            DebugCommand::Finish => Some(self.finish()?),
+            DebugCommand::Continue => Some(self.resume()?),
+            _ => None,
+        };
+        if let Some(stop) = stop {
+            return Ok(self.stopped(stop));
+        }
+        Ok(match command {
+            DebugCommand::Step(_)
+            | DebugCommand::Next
+            | DebugCommand::Finish
+            | DebugCommand::Continue
+            | DebugCommand::Quit => String::new(),
+            DebugCommand::Break(b) => {
+                self.add_breakpoint(b);
+                format!("breakpoint {}: {b}", self.breakpoints.len() - 1)
+            }
+            DebugCommand::Watch(w) => {
+                self.add_watch(w);
+                format!("watch {}: {w}", self.watches.len() - 1)
+            }
+            DebugCommand::Delete(n) => match self.remove_breakpoint(n) {
+                Some(b) => format!("deleted breakpoint {b}"),
+                None => bail!("no breakpoint {n}"),
+            },
+            DebugCommand::Unwatch(n) => match self.remove_watch(n) {
+                Some(w) => format!("deleted watch {w}"),
+                None => bail!("no watch {n}"),
+            },
+            DebugCommand::List => {
+                let mut out = String::new();
+                for (n, b) in self.breakpoints.iter().enumerate() {
+                    _ = writeln!(out, "breakpoint {n}: {b}");
+                }
+                for (n, w) in self.watches().enumerate() {
+                    _ = writeln!(out, "watch {n}: {w}");
+                }
+                out
+            }
+            DebugCommand::Registers => self.registers(),
+            DebugCommand::Memory(addr, len) => self.memory(addr.unwrap_or(self.proc.i()), len),
+            DebugCommand::Stack => self.stack(),
+            DebugCommand::Disassemble(addr, n) => {
+                self.disassemble(addr.unwrap_or(self.proc.program_counter()), n)
+            }
+            DebugCommand::Screen => self.screen(),
+            DebugCommand::Key(k, pressed) => {
+                self.proc.set_key(k, pressed);
+                format!("key {k:X} {}", if pressed { "down" } else { "up" })
+            }
+            DebugCommand::Help => DebugCommand::HELP.to_string(),
+        })
+    }
+
+    /// why execution stopped, and the op it stopped on
+    fn stopped(&self, stop: Stop) -> String {
+        format!(
+            "{stop}\n{}",
+            self.disassemble(self.proc.program_counter(), 1)
+        )
+    }
+}
+
+fn word(mem: &[u8], addr: u16) -> Option<u16> {
+    let w = mem.get(addr as usize..addr as usize + 2)?;
+    Some(u16::from_be_bytes([w[0], w[1]]))
+}
+
+/// a line typed at the debugger prompt
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DebugCommand {
+    Step(u64),
+    Next,
+    Finish,
+    Continue,
+    Break(Breakpoint),
+    Watch(Watch),
+    Delete(usize),
+    Unwatch(usize),
+    List,
+    Registers,
+    Memory(Option<u16>, usize),
+    Stack,
+    Disassemble(Option<u16>, usize),
+    Screen,
+    Key(u8, bool),
+    Help,
+    Quit,
+}
+
+impl DebugCommand {
+    pub const HELP: &'static str = "\
+s, step [n]          run n ops, 1 by default
+n, next              run one op, running a CALL through to its return
+f, finish            run until the current subroutine returns
+c, continue          run until a breakpoint or watch stops execution
+b, break <addr>      stop when PC reaches addr
+b, break op <nnnn>   stop on ops matching the pattern, non hex digits match anything
+w, watch <Vx|I|addr> stop when a register or memory byte changes
+d, delete <n>        delete breakpoint n
+unwatch <n>          delete watch n
+l, list              list breakpoints and watches
+r, regs              dump registers, I, PC, SP and timers
+m, mem [addr] [len]  dump memory, from I by default
+bt, stack            dump the call stack
+dis [addr] [n]       disassemble n ops, from PC by default
+screen               print the display
+key <k> <down|up>    press or release hex key k
+q, quit              leave the debugger";
+
+    pub fn parse(line: &str) -> Result<Self> {
+        let mut words = line.split_whitespace();
+        let command = words.next().unwrap_or("step");
+        let args: Vec<&str> = words.collect();
+        let arg = |n: usize| args.get(n).copied();
+        let addr = |n: usize| arg(n).map(number).transpose();
+        let count = |n: usize, default: u64| -> Result<u64> {
+            Ok(arg(n).map(number).transpose()?.map_or(default, u64::from))
+        };
+        Ok(match command {
+            "s" | "step" => DebugCommand::Step(count(0, 1)?),
+            "n" | "next" => DebugCommand::Next,
+            "f" | "finish" => DebugCommand::Finish,
+            "c" | "continue" => DebugCommand::Continue,
+            "b" | "break" => match (arg(0), arg(1)) {
+                (Some("op"), Some(pattern)) => DebugCommand::Break(Breakpoint::opcode(pattern)?),
+                (Some(a), None) => DebugCommand::Break(Breakpoint::Addr(number(a)?)),
+                _ => bail!("usage: break <addr> | break op <pattern>"),
+            },
+            "w" | "watch" => DebugCommand::Watch(match arg(0) {
+                Some("i" | "I") => Watch::I,
+                Some(r) if r.len() == 2 && r.to_lowercase().starts_with('v') => {
+                    Watch::Register(u8::from_str_radix(&r[1..], 16)?)
+                }
+                Some(a) => Watch::Memory(number(a)?),
+                None => bail!("usage: watch <Vx|I|addr>"),
+            }),
+            "d" | "delete" => DebugCommand::Delete(count(0, 0)? as usize),
+            "unwatch" => DebugCommand::Unwatch(count(0, 0)? as usize),
+            "l" | "list" => DebugCommand::List,
+            "r" | "regs" => DebugCommand::Registers,
+            "m" | "mem" => DebugCommand::Memory(addr(0)?, count(1, 64)? as usize),
+            "bt" | "stack" => DebugCommand::Stack,
+            "dis" => DebugCommand::Disassemble(addr(0)?, count(1, 8)? as usize),
+            "screen" => DebugCommand::Screen,
+            "key" => {
+                let key = arg(0).ok_or_else(|| eyre!("usage: key <k> <down|up>"))?;
+                let pressed = match arg(1) {
+                    Some("down") | None => true,
+                    Some("up") => false,
+                    Some(s) => bail!("expected down or up, not `{s}`"),
+                };
+                DebugCommand::Key(u8::from_str_radix(key, 16)? & 0xF, pressed)
+            }
+            "h" | "help" | "?" => DebugCommand::Help,
+            "q" | "quit" | "exit" => DebugCommand::Quit,
+            _ => bail!("unknown command `{command}`, try `help`"),
+        })
+    }
+}
+
+/// address or count, hex with a `0x` or `$` prefix, decimal otherwise
+fn number(s: &str) -> Result<u16> {
+    let n = match s.strip_prefix("0x").or_else(|| s.strip_prefix('$')) {
+        Some(hex) => u16::from_str_radix(hex, 16),
+        None => s.parse(),
+    };
+    n.map_err(|e| eyre!("bad number `{s}`: {e}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::asm::assemble;
+
+    fn debugger(src: &str) -> Debugger {
+        Debugger::new(Processor::with_rom(assemble(src).unwrap()), 700)
+    }
+
+    const PROGRAM: &str = "
+                LD V0, 1
+                CALL sub
+                LD V2, 3
+        loop:   JP loop
+        sub:    LD V1, 2
+                CALL inner
+                RET
+        inner:  LD I, 0x300
+                LD [I], V1
+                RET
+        ";
+
+    #[test]
+    fn step_over_and_finish() {
+        let mut d = debugger(PROGRAM);
+        assert_eq!(d.step(1).unwrap(), Stop::Done);
+        assert_eq!(d.step_over().unwrap(), Stop::Done);
+        assert_eq!(d.processor().program_counter(), 0x204);
+        assert_eq!(d.processor().registers()[1], 2);
+
+        let mut d = debugger(PROGRAM);
+        d.step(4).unwrap();
+        assert_eq!(d.processor().stack(), [0x204, 0x20C]);
+        assert_eq!(d.finish().unwrap(), Stop::Done);
+        assert_eq!(d.processor().program_counter(), 0x20C);
+        assert!(d.finish().is_ok() && d.finish().is_err());
+    }
+
+    #[test]
+    fn breakpoints_and_watches_stop_execution() {
+        let mut d = debugger(PROGRAM);
+        d.add_breakpoint(Breakpoint::opcode("Fx55").unwrap());
+        assert_eq!(
+            d.resume().unwrap(),
+            Stop::Breakpoint(Breakpoint::Opcode {
+                value: 0xF055,
+                mask: 0xF0FF
+            })
+        );
+        assert_eq!(d.processor().program_counter(), 0x210);
+        d.remove_breakpoint(0);
+        d.add_watch(Watch::Memory(0x300));
+        assert_eq!(
+            d.resume().unwrap(),
+            Stop::Watch {
+                watch: Watch::Memory(0x300),
+                old: 0,
+                new: 1
+            }
+        );
+        d.add_breakpoint(Breakpoint::Addr(0x206));
+        assert_eq!(
+            d.resume().unwrap(),
+            Stop::Breakpoint(Breakpoint::Addr(0x206))
+        );
+        assert_eq!(
+            d.resume().unwrap(),
+            Stop::Breakpoint(Breakpoint::Addr(0x206))
+        );
+        d.remove_breakpoint(0);
+        assert_eq!(d.resume().unwrap(), Stop::Limit(700 * 60));
+    }
+
+    #[test]
+    fn timers_tick_60_times_a_second() {
+        let mut d = debugger("loop: JP loop");
+        let mut s = d.processor().snapshot();
+        s.delay_timer = 255;
+        d.processor_mut().restore(s);
+        assert_eq!(d.step(700).unwrap(), Stop::Done);
+        assert_eq!(d.processor().delay_timer(), 255 - 60);
+    }
+
+    #[test]
+    fn parses_commands() {
+        assert_eq!(DebugCommand::parse("").unwrap(), DebugCommand::Step(1));
+        assert_eq!(DebugCommand::parse("s 10").unwrap(), DebugCommand::Step(10));
+        assert_eq!(
+            DebugCommand::parse("b 0x20A").unwrap(),
+            DebugCommand::Break(Breakpoint::Addr(0x20A))
+        );
+        assert_eq!(
+            DebugCommand::parse("watch vA").unwrap(),
+            DebugCommand::Watch(Watch::Register(0xA))
+        );
+        assert_eq!(
+            DebugCommand::parse("m $300 4").unwrap(),
+            DebugCommand::Memory(Some(0x300), 4)
+        );
+        assert!(DebugCommand::parse("b op 12345").is_err());
+        assert!(DebugCommand::parse("frobnicate").is_err());
+    }
+}
@@ -0,0 +1,473 @@
+use std::{collections::BTreeMap, fmt::Write};
+
+use clap::ValueEnum;
+use color_eyre::Result;
+use serde::Serialize;
+
+use super::{
+    asm::ORIGIN,
+    instructions::{Instruction, Row},
+    rom::Rom,
+};
+
+/// what a rom byte was found to be
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EntryKind {
+    /// an instruction reachable from the entry point
+    Code(Instruction),
+    /// a byte no path of execution reaches, usually sprite data
+    Data,
+}
+
+/// one line of a disassembly
+#[derive(Debug, Clone)]
+pub struct Entry {
+    pub addr: u16,
+    /// 2 bytes for an op, 4 for a XO-CHIP long load, 1 for data
+    pub bytes: Vec<u8>,
+    pub kind: EntryKind,
+}
+
+/// how an instruction refers to another address
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum RefKind {
+    /// `LD I`, the address is usually sprite data
+    Load,
+    /// `JP` or a computed `JP V0`
+    Jump,
+    /// `CALL`
+    Call,
+}
+
+/// how a disassembly is printed
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Format {
+    /// fixed width table for reading
+    #[default]
+    Table,
+    /// source the assembler can rebuild the rom from
+    Asm,
+    /// an array of `Record`s
+    Json,
+    /// one `Record` per line, with a header
+    Csv,
+}
+
+/// an entry flattened for tooling, stable across runs of the same rom
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Record {
+    pub address: u16,
+    /// the entry bytes in hex
+    pub raw: String,
+    pub label: Option<String>,
+    /// `code` or `data`
+    pub kind: &'static str,
+    pub mnemonic: String,
+    pub operands: Vec<String>,
+    /// address a jump, call or `LD I` refers to
+    pub target: Option<u16>,
+    /// addresses of the instructions referring to this entry
+    pub xrefs: Vec<u16>,
+}
+
+/// Disassembly
+///
+/// a rom split into code and data by following every jump, call and skip
+/// from the entry point, everything never reached is treated as data.
+#[derive(Debug, Clone)]
+pub struct Disassembly {
+    /// address the rom is loaded at
+    pub base: u16,
+    pub entries: Vec<Entry>,
+    /// names for referenced addresses, only ever at the start of an entry
+    pub labels: BTreeMap<u16, String>,
+    /// instructions referring to each address
+    pub xrefs: BTreeMap<u16, Vec<(u16, RefKind)>>,
+}
+
+pub fn disassemble(rom: &Rom) -> Disassembly {
+    let base = rom.load_addr() as usize;
+    let bytes = rom.bytes();
+    let word = |a: usize| -> Option<u16> {
+        let w = bytes.get(a.checked_sub(base)?..a - base + 2)?;
+        Some(u16::from_be_bytes([w[0], w[1]]))
+    };
+
+    let mut code = BTreeMap::new();
+    let mut covered = vec![false; bytes.len()];
+    let mut xrefs: BTreeMap<u16, Vec<(u16, RefKind)>> = BTreeMap::new();
+    let mut xref = |target: u16, from: usize, kind| {
+        xrefs.entry(target).or_default().push((from as u16, kind));
+    };
+    let mut work = vec![base];
+    while let Some(mut addr) = work.pop() {
+        loop {
+            let Some(Ok(inst)) = word(addr).map(Instruction::decode) else {
+                break;
+            };
+            let len = if inst == Instruction::LDILong { 4 } else { 2 };
+            // stop at visited code, or anything overlapping it
+            match covered.get(addr - base..addr - base + len) {
+                Some(c) if !c.contains(&true) => c.len(),
+                _ => break,
+            };
+            covered[addr - base..addr - base + len].fill(true);
+            let next = addr + len;
+            match inst {
+                Instruction::JPAddr(t) => {
+                    xref(t, addr, RefKind::Jump);
+                    work.push(t as usize);
+                }
+                // most likely a jump table, follow the V0 = 0 entry
+                Instruction::JPV0Addr(t) => {
+                    xref(t, addr, RefKind::Jump);
+                    work.push(t as usize);
+                }
+                Instruction::CALLAddr(t) => {
+                    xref(t, addr, RefKind::Call);
+                    work.push(t as usize);
+                }
+                Instruction::SExByte(..)
+                | Instruction::SNExByte(..)
+                | Instruction::SExy(..)
+                | Instruction::SNExy(..)
+                | Instruction::SKPx(_)
+                | Instruction::SKPNPx(_) => {
+                    let skipped = if word(next) == Some(0xF000) { 4 } else { 2 };
+                    work.push(next + skipped);
+                }
+                Instruction::LDIAddr(t) => xref(t, addr, RefKind::Load),
+                Instruction::LDILong => {
+                    if let Some(t) = word(addr + 2) {
+                        xref(t, addr, RefKind::Load);
+                    }
+                }
+                _ => {}
+            }
+            let ends_flow = matches!(
+                inst,
+                Instruction::JPAddr(_)
+                    | Instruction::JPV0Addr(_)
+                    | Instruction::RET
+                    | Instruction::EXIT
+            );
+            code.insert(addr, (inst, len));
+            if ends_flow {
+                break;
+            }
+            addr = next;
+        }
+    }
+
+    let mut entries = vec![];
+    let mut offset = 0;
+    while offset < bytes.len() {
+        let addr = base + offset;
+        let (kind, len) = match code.remove(&addr) {
+            Some((inst, len)) => (EntryKind::Code(inst), len),
+            None => (EntryKind::Data, 1),
+        };
+        entries.push(Entry {
+            addr: addr as u16,
+            bytes: bytes[offset..offset + len].to_vec(),
+            kind,
+        });
+        offset += len;
+    }
+
+    let mut labels = BTreeMap::from([(base as u16, "start".to_string())]);
+    for (target, refs) in &xrefs {
+        let starts_entry = entries.binary_search_by_key(target, |e| e.addr).is_ok();
+        if !starts_entry || labels.contains_key(target) {
+            continue;
+        }
+        let prefix = match refs.iter().map(|(_, k)| *k).max() {
+            Some(RefKind::Call) => "sub",
+            Some(RefKind::Jump) => "label",
+            _ => "data",
+        };
+        labels.insert(*target, format!("{prefix}_{target:03X}"));
+    }
+
+    Disassembly {
+        base: base as u16,
+        entries,
+        labels,
+        xrefs,
+    }
+}
+
+/// a data byte drawn as a row of sprite pixels
+pub fn sprite_row(b: u8) -> String {
+    (0..8)
+        .map(|i| if b & (0x80 >> i) != 0 { '#' } else { '.' })
+        .collect()
+}
+
+impl Disassembly {
+    /// label to use in place of `addr` in operands
+    ///
+    /// source only re-assembles to the same rom when loaded at the
+    /// assembler origin, so labels are not used for other bases.
+    fn operand_label(&self, addr: u16) -> Option<&str> {
+        (self.base == ORIGIN)
+            .then(|| self.labels.get(&addr).map(String::as_str))
+            .flatten()
+    }
+
+    /// address an entry jumps to, calls or loads into I
+    fn target(entry: &Entry) -> Option<u16> {
+        match &entry.kind {
+            EntryKind::Code(
+                Instruction::JPAddr(t)
+                | Instruction::JPV0Addr(t)
+                | Instruction::CALLAddr(t)
+                | Instruction::LDIAddr(t),
+            ) => Some(*t),
+            EntryKind::Code(Instruction::LDILong) => {
+                Some(u16::from_be_bytes([entry.bytes[2], entry.bytes[3]]))
+            }
+            _ => None,
+        }
+    }
+
+    /// assembler text of an entry, with labels in place of addresses
+    pub fn text(&self, entry: &Entry) -> String {
+        let inst = match &entry.kind {
+            EntryKind::Data => return format!("db 0x{:02X}", entry.bytes[0]),
+            EntryKind::Code(inst) => inst,
+        };
+        let target = Self::target(entry);
+        let label = target.and_then(|t| self.operand_label(t));
+        match (inst, label) {
+            (Instruction::JPAddr(_), Some(l)) => format!("JP {l}"),
+            (Instruction::JPV0Addr(_), Some(l)) => format!("JP V0, {l}"),
+            (Instruction::CALLAddr(_), Some(l)) => format!("CALL {l}"),
+            (Instruction::LDIAddr(_), Some(l)) => format!("LD I, {l}"),
+            (Instruction::LDILong, Some(l)) => format!("LD I, LONG {l}"),
+            (Instruction::LDILong, None) => format!("LD I, LONG 0x{:04X}", target.unwrap_or(0)),
+            (inst, _) => inst.asm(),
+        }
+    }
+
+    /// print in the given format, `sprites` adds sprite rows to data in
+    /// the table and asm formats
+    pub fn format(&self, format: Format, sprites: bool) -> Result<String> {
+        Ok(match format {
+            Format::Table => self.table(sprites),
+            Format::Asm => self.source(sprites),
+            Format::Json => serde_json::to_string_pretty(&self.records())? + "\n",
+            Format::Csv => self.csv()?,
+        })
+    }
+
+    pub fn records(&self) -> Vec<Record> {
+        self.entries
+            .iter()
+            .map(|entry| {
+                let text = self.text(entry);
+                let (mnemonic, operands) = text.split_once(' ').unwrap_or((&text, ""));
+                Record {
+                    address: entry.addr,
+                    raw: entry.bytes.iter().map(|b| format!("{b:02X}")).collect(),
+                    label: self.labels.get(&entry.addr).cloned(),
+                    kind: match entry.kind {
+                        EntryKind::Code(_) => "code",
+                        EntryKind::Data => "data",
+                    },
+                    mnemonic: mnemonic.to_string(),
+                    operands: operands
+                        .split(", ")
+                        .filter(|o| !o.is_empty())
+                        .map(str::to_string)
+                        .collect(),
+                    target: Self::target(entry),
+                    xrefs: self
+                        .xrefs
+                        .get(&entry.addr)
+                        .map(|r| r.iter().map(|(from, _)| *from).collect())
+                        .unwrap_or_default(),
+                }
+            })
+            .collect()
+    }
+
+    /// records as csv, addresses in hex and lists space separated
+    pub fn csv(&self) -> Result<String> {
+        let hex = |a: &u16| format!("0x{a:03X}");
+        let mut w = csv::Writer::from_writer(vec![]);
+        w.write_record([
+            "address", "raw", "label", "kind", "mnemonic", "operands", "target", "xrefs",
+        ])?;
+        for r in self.records() {
+            w.write_record([
+                hex(&r.address),
+                r.raw,
+                r.label.unwrap_or_default(),
+                r.kind.to_string(),
+                r.mnemonic,
+                r.operands.join(" "),
+                r.target.as_ref().map(hex).unwrap_or_default(),
+                r.xrefs.iter().map(hex).collect::<Vec<_>>().join(" "),
+            ])?;
+        }
+        Ok(String::from_utf8(w.into_inner()?)?)
+    }
+
+    /// fixed width table of every entry, with the callers of each address
+    pub fn table(&self, sprites: bool) -> String {
+        let mut out = String::new();
+        let art = if sprites { "Sprite   | " } else { "" };
+        _ = writeln!(
+            out,
+            "  Addr |  Word |   Name |  Addr |  Byte |  Reg1 |  Reg2 |     N | {art}Refs\n{}",
+            "-".repeat(80)
+        );
+        for entry in &self.entries {
+            if let Some(label) = self.labels.get(&entry.addr) {
+                _ = writeln!(out, "{label}:");
+            }
+            let raw = entry
+                .bytes
+                .iter()
+                .take(2)
+                .fold(String::new(), |s, b| s + &format!("{b:02X}"));
+            let row = match &entry.kind {
+                EntryKind::Code(inst) => format!("{inst}"),
+                EntryKind::Data => format!("{}", Row::data(entry.bytes[0])),
+            };
+            let refs = self
+                .xrefs
+                .get(&entry.addr)
+                .map(|r| {
+                    r.iter()
+                        .map(|(from, _)| format!("0x{from:03X}"))
+                        .collect::<Vec<_>>()
+                        .join(",")
+                })
+                .unwrap_or_default();
+            let art = match (&entry.kind, sprites) {
+                (EntryKind::Data, true) => format!("{} | ", sprite_row(entry.bytes[0])),
+                (_, true) => format!("{:8} | ", ""),
+                _ => String::new(),
+            };
+            _ = writeln!(
+                out,
+                " 0x{:03X} | {raw:>5} | {row} | {art}{refs}",
+                entry.addr
+            );
+        }
+        out
+    }
+
+    /// source that assembles back into the same rom
+    pub fn source(&self, sprites: bool) -> String {
+        let mut out = format!("; loaded at 0x{:03X}\n", self.base);
+        for entry in &self.entries {
+            if let Some(label) = self.labels.get(&entry.addr) {
+                _ = writeln!(out, "{label}:");
+            }
+            let text = self.text(entry);
+            match (&entry.kind, sprites) {
+                (EntryKind::Data, true) => {
+                    _ = writeln!(out, "    {text:<24}; {}", sprite_row(entry.bytes[0]))
+                }
+                _ => _ = writeln!(out, "    {text}"),
+            }
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::asm::assemble;
+
+    const PROGRAM: &str = "
+        start:  LD I, sprite
+                CALL draw
+                SE V0, 1
+                JP start
+                LD I, LONG sprite
+        loop:   JP loop
+        draw:   DRW V0, V1, 2
+                RET
+        sprite: db 0xF0, 0x90
+        ";
+
+    #[test]
+    fn follows_flow_and_separates_data() {
+        let rom = assemble(PROGRAM).unwrap();
+        let d = disassemble(&rom);
+        let code: Vec<_> = d
+            .entries
+            .iter()
+            .filter(|e| matches!(e.kind, EntryKind::Code(_)))
+            .map(|e| e.addr)
+            .collect();
+        assert_eq!(
+            code,
+            [0x200, 0x202, 0x204, 0x206, 0x208, 0x20C, 0x20E, 0x210]
+        );
+        let data = d
+            .entries
+            .iter()
+            .filter(|e| e.kind == EntryKind::Data)
+            .count();
+        assert_eq!(data, 2);
+        assert_eq!(d.labels[&0x20E], "sub_20E");
+        assert_eq!(d.labels[&0x20C], "label_20C");
+        assert_eq!(d.labels[&0x212], "data_212");
+    }
+
+    #[test]
+    fn source_reassembles_to_the_same_rom() {
+        for src in [PROGRAM, "db 0x00, 0xE0\nCLS"] {
+            let rom = assemble(src).unwrap();
+            let out = disassemble(&rom).source(true);
+            assert_eq!(assemble(&out).unwrap().bytes(), rom.bytes(), "{out}");
+        }
+        let rom = Rom::load_from_path("../../test-data/test.chip8.rom").unwrap();
+        let out = disassemble(&rom).source(true);
+        assert_eq!(assemble(&out).unwrap().bytes(), rom.bytes(), "{out}");
+    }
+
+    #[test]
+    fn records_carry_operands_and_xrefs() {
+        let d = disassemble(&assemble(PROGRAM).unwrap());
+        let records = d.records();
+        assert_eq!(
+            records[1],
+            Record {
+                address: 0x202,
+                raw: "220E".into(),
+                label: None,
+                kind: "code",
+                mnemonic: "CALL".into(),
+                operands: vec!["sub_20E".into()],
+                target: Some(0x20E),
+                xrefs: vec![],
+            }
+        );
+        let sprite = records.iter().find(|r| r.address == 0x212).unwrap();
+        assert_eq!(
+            (sprite.kind, sprite.xrefs.as_slice()),
+            ("data", &[0x200, 0x208][..])
+        );
+
+        let json: serde_json::Value =
+            serde_json::from_str(&d.format(Format::Json, false).unwrap()).unwrap();
+        assert_eq!(json[2]["operands"], serde_json::json!(["V0", "0x01"]));
+        let csv = d.format(Format::Csv, false).unwrap();
+        assert_eq!(
+            csv.lines().nth(1),
+            Some("0x200,A212,start,code,LD,I data_212,0x212,0x206")
+        );
+    }
+
+    #[test]
+    fn sprite_rows_render_bits() {
+        assert_eq!(sprite_row(0b1001_0110), "#..#.##.");
+    }
+}
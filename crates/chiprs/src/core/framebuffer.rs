@@ -1,67 +1,293 @@
-use color_eyre::eyre::Result;
-use pixels::{Pixels, SurfaceTexture};
-
 use bit_field::BitField;
+use serde::{Deserialize, Serialize};
+
+/// display width in pixels
+pub const WIDTH: usize = 64;
+/// display height in pixels
+pub const HEIGHT: usize = 32;
+/// SUPER-CHIP hires display width in pixels
+pub const HIRES_WIDTH: usize = 128;
+/// SUPER-CHIP hires display height in pixels
+pub const HIRES_HEIGHT: usize = 64;
+
+/// what happens to sprite pixels that go past the screen edge
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum EdgeMode {
+    /// drop them, what the COSMAC VIP does
+    #[default]
+    Clip,
+    /// draw them on the opposite edge
+    Wrap,
+}
+
+/// number of XO-CHIP bit-planes
+pub const PLANES: usize = 2;
+
+/// rgba for each palette index: unlit, plane 0, plane 1 and both planes lit
+pub const PALETTE: [[u8; 4]; 1 << PLANES] = [
+    [0x10, 0x10, 0x10, 0xFF],
+    [0xE0, 0xE0, 0xE0, 0xFF],
+    [0xE0, 0x60, 0x20, 0xFF],
+    [0x60, 0x20, 0x10, 0xFF],
+];
 
 /// FrameBuffer
-#[derive(Debug)]
+///
+/// bit-planes of the chip8 display, independent of any window, stored
+/// row-major as one byte per pixel with bit `n` holding plane `n`.
+/// 64x32, or 128x64 in hires mode. plain chip8 only ever uses plane 0,
+/// XO-CHIP can select both for a 4 colour display.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct FrameBuffer {
-    p: pixels::Pixels,
-    width: u32,
-    height: u32,
+    pixels: Vec<u8>,
+    width: usize,
+    height: usize,
+    edge: EdgeMode,
+    /// mask of the planes drawing, clearing and scrolling act on
+    planes: u8,
+}
+
+impl Default for FrameBuffer {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl FrameBuffer {
-    pub fn resize(&mut self, width: u32, height: u32) -> bool {
-        self.p.resize_surface(width, height).is_ok()
+    pub fn new() -> Self {
+        Self {
+            pixels: vec![0; WIDTH * HEIGHT],
+            width: WIDTH,
+            height: HEIGHT,
+            edge: EdgeMode::default(),
+            planes: 1,
+        }
+    }
+    pub fn edge_mode(&self) -> EdgeMode {
+        self.edge
+    }
+    pub fn set_edge_mode(&mut self, edge: EdgeMode) {
+        self.edge = edge;
+    }
+    pub fn planes(&self) -> u8 {
+        self.planes
     }
+    /// select the planes later ops act on, XO-CHIP `plane n`
+    pub fn set_planes(&mut self, mask: u8) {
+        self.planes = mask & ((1 << PLANES) - 1);
+    }
+    pub fn hires(&self) -> bool {
+        self.width == HIRES_WIDTH
+    }
+    /// switch between 64x32 and 128x64, clearing every plane
+    pub fn set_hires(&mut self, hires: bool) {
+        (self.width, self.height) = if hires {
+            (HIRES_WIDTH, HIRES_HEIGHT)
+        } else {
+            (WIDTH, HEIGHT)
+        };
+        self.pixels = vec![0; self.width * self.height];
+    }
+    /// one of the two resolutions, with a pixel for every position
+    pub(crate) fn is_valid(&self) -> bool {
+        matches!(
+            (self.width, self.height),
+            (WIDTH, HEIGHT) | (HIRES_WIDTH, HIRES_HEIGHT)
+        ) && self.pixels.len() == self.width * self.height
+    }
+    pub fn width(&self) -> usize {
+        self.width
+    }
+    pub fn height(&self) -> usize {
+        self.height
+    }
+    /// whether any plane is lit at (x, y), `false` when out of bounds
+    pub fn get(&self, x: usize, y: usize) -> bool {
+        self.color(x, y) != 0
+    }
+    /// palette index at (x, y), the planes lit there as a bitmask
+    pub fn color(&self, x: usize, y: usize) -> u8 {
+        if x < self.width && y < self.height {
+            self.pixels[y * self.width + x]
+        } else {
+            0
+        }
+    }
+    /// palette indices of every pixel, row by row
+    pub fn pixels(&self) -> &[u8] {
+        &self.pixels
+    }
+    /// iterate over the rows of palette indices, top to bottom
+    pub fn rows(&self) -> impl Iterator<Item = &[u8]> {
+        self.pixels.chunks_exact(self.width)
+    }
+    /// XOR an 8 pixel wide sprite onto the plane at (x, y), one byte per row
+    ///
+    /// the origin always wraps around the screen, pixels going past the
+    /// edge are clipped or wrapped based on the `EdgeMode`. with several
+    /// planes selected `ns` holds one sprite per plane, lowest plane first.
+    /// returns true if any lit pixel was turned off.
     pub fn draw_at(&mut self, x: u8, y: u8, ns: &[u8]) -> bool {
-        let mut f = false;
-        let frame = self.p.frame_mut();
-        let width = self.width;
-        for (row_idx, row) in frame.chunks_exact_mut(width as usize * 4).enumerate() {
-            for (col_idx, pixel) in row.chunks_exact_mut(4).enumerate() {
-                if col_idx >= x.into()
-                    && (col_idx < x as usize + 8)
-                    && row_idx >= y.into()
-                    && (row_idx < y as usize + ns.len())
-                {
-                    pixel[0] = 100;
-                    pixel[1] = 100;
-                    pixel[2] = 100;
-                    pixel[3] = 255;
+        let mut collision = false;
+        for (bit, sprite) in self.selected().zip(ns.chunks(self.sprite_len(ns))) {
+            let rows = sprite.iter().map(|b| (*b as u16) << 8);
+            collision |= self.draw(x, y, 8, bit, rows);
+        }
+        collision
+    }
+    /// XOR a 16x16 SUPER-CHIP sprite, two bytes per row
+    pub fn draw_wide_at(&mut self, x: u8, y: u8, ns: &[u8]) -> bool {
+        let mut collision = false;
+        for (bit, sprite) in self.selected().zip(ns.chunks(self.sprite_len(ns))) {
+            let rows = sprite
+                .chunks_exact(2)
+                .map(|b| ((b[0] as u16) << 8) | b[1] as u16);
+            collision |= self.draw(x, y, 16, bit, rows);
+        }
+        collision
+    }
+    /// bits of the selected planes, lowest first
+    fn selected(&self) -> impl Iterator<Item = u8> {
+        let planes = self.planes;
+        (0..PLANES as u8)
+            .map(|p| 1 << p)
+            .filter(move |bit| planes & bit != 0)
+    }
+    /// bytes of `ns` belonging to each selected plane
+    fn sprite_len(&self, ns: &[u8]) -> usize {
+        (ns.len() / self.planes.count_ones().max(1) as usize).max(1)
+    }
+    /// draw rows of `width` bits, read from the high bit of each u16
+    fn draw(
+        &mut self,
+        x: u8,
+        y: u8,
+        width: usize,
+        bit: u8,
+        rows: impl Iterator<Item = u16>,
+    ) -> bool {
+        let (x, y) = (x as usize % self.width, y as usize % self.height);
+        let mut collision = false;
+        for (row, bits) in rows.enumerate() {
+            for col in 0..width {
+                if !bits.get_bit(15 - col) {
+                    continue;
+                }
+                let (mut px, mut py) = (x + col, y + row);
+                if px >= self.width || py >= self.height {
+                    match self.edge {
+                        EdgeMode::Clip => continue,
+                        EdgeMode::Wrap => {
+                            px %= self.width;
+                            py %= self.height;
+                        }
+                    }
                 }
-                //for xi in 0..8 {
-                //    f = f || collide_get(&mut x.data[k as usize + i], *n, j, xi);
-                //}
+                let pixel = &mut self.pixels[py * self.width + px];
+                collision |= *pixel & bit != 0;
+                *pixel ^= bit;
+            }
+        }
+        collision
+    }
+    /// move the selected planes down `n` rows, blanking the top
+    pub fn scroll_down(&mut self, n: usize) {
+        self.shift(0, n as isize);
+    }
+    /// move the selected planes up `n` rows, blanking the bottom
+    pub fn scroll_up(&mut self, n: usize) {
+        self.shift(0, -(n as isize));
+    }
+    /// move the selected planes left `n` columns, blanking the right edge
+    pub fn scroll_left(&mut self, n: usize) {
+        self.shift(-(n as isize), 0);
+    }
+    /// move the selected planes right `n` columns, blanking the left edge
+    pub fn scroll_right(&mut self, n: usize) {
+        self.shift(n as isize, 0);
+    }
+    fn shift(&mut self, dx: isize, dy: isize) {
+        let (w, h, mask) = (self.width as isize, self.height as isize, self.planes);
+        let old = self.pixels.clone();
+        for y in 0..h {
+            for x in 0..w {
+                let (sx, sy) = (x - dx, y - dy);
+                let src = if (0..w).contains(&sx) && (0..h).contains(&sy) {
+                    old[(sy * w + sx) as usize]
+                } else {
+                    0
+                };
+                let pixel = &mut self.pixels[(y * w + x) as usize];
+                *pixel = (*pixel & !mask) | (src & mask);
             }
         }
-        _ = self.p.render();
-        f
     }
-    pub fn new(window: &winit::window::Window) -> Result<Self> {
-        let width = 32;
-        let height = 64;
+    /// blank the selected planes
+    pub fn clear(&mut self) {
+        let mask = self.planes;
+        self.pixels.iter_mut().for_each(|p| *p &= !mask);
+    }
+}
 
-        let size = window.inner_size();
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-        Ok(FrameBuffer {
-            p: Pixels::new(
-                width,
-                height,
-                SurfaceTexture::new(size.width, size.height, &window),
-            )?,
-            width,
-            height,
-        })
+    #[test]
+    fn draw_xors_and_reports_collision() {
+        let mut fb = FrameBuffer::new();
+        assert!(!fb.draw_at(0, 0, &[0b1010_0000]));
+        assert!(fb.get(0, 0) && !fb.get(1, 0) && fb.get(2, 0));
+        assert!(fb.draw_at(0, 0, &[0b1000_0000]));
+        assert!(!fb.get(0, 0) && fb.get(2, 0));
+        assert!(!fb.draw_at(1, 0, &[0b1000_0000]));
     }
-    pub fn clear(&mut self) {
-        self.p.clear_color(pixels::wgpu::Color {
-            r: 0.0,
-            g: 0.0,
-            b: 0.0,
-            a: 1.0,
-        });
-        _ = self.p.render();
+
+    #[test]
+    fn origin_wraps_sprite_clips() {
+        let mut fb = FrameBuffer::new();
+        fb.draw_at(WIDTH as u8 + 60, 31, &[0xFF, 0xFF]);
+        assert!(fb.get(60, 31) && fb.get(63, 31));
+        assert!(!fb.get(0, 31) && !fb.get(60, 0));
+    }
+
+    #[test]
+    fn wrap_mode_wraps_sprite() {
+        let mut fb = FrameBuffer::new();
+        fb.set_edge_mode(EdgeMode::Wrap);
+        fb.draw_at(60, 31, &[0xFF, 0xFF]);
+        assert!(fb.get(63, 31) && fb.get(0, 31) && fb.get(3, 31));
+        assert!(fb.get(60, 0) && fb.get(3, 0));
+    }
+
+    #[test]
+    fn hires_wide_sprite_and_scroll() {
+        let mut fb = FrameBuffer::new();
+        fb.set_hires(true);
+        assert_eq!((fb.width(), fb.height()), (HIRES_WIDTH, HIRES_HEIGHT));
+        fb.draw_wide_at(100, 0, &[0x80, 0x01]);
+        assert!(fb.get(100, 0) && fb.get(115, 0));
+        fb.scroll_down(2);
+        assert!(!fb.get(100, 0) && fb.get(100, 2));
+        fb.scroll_right(4);
+        assert!(fb.get(104, 2) && fb.get(119, 2));
+        fb.scroll_left(8);
+        assert!(fb.get(96, 2) && fb.get(111, 2));
+        fb.scroll_up(2);
+        assert!(fb.get(96, 0) && !fb.get(96, 2));
+    }
+
+    #[test]
+    fn planes_draw_and_clear_independently() {
+        let mut fb = FrameBuffer::new();
+        fb.set_planes(0b11);
+        fb.draw_at(0, 0, &[0b1100_0000, 0b1000_0000]);
+        assert_eq!((fb.color(0, 0), fb.color(1, 0)), (0b11, 0b01));
+        fb.set_planes(0b10);
+        assert!(fb.draw_at(0, 0, &[0b1000_0000]));
+        assert_eq!(fb.color(0, 0), 0b01);
+        fb.set_planes(0b01);
+        fb.clear();
+        assert!(!fb.get(0, 0) && !fb.get(1, 0));
     }
 }
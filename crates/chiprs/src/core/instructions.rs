@@ -1,6 +1,6 @@
 use color_eyre::{eyre::bail, Result};
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Instruction {
     // STARTS with 0
     ///00E0 - CLS
@@ -10,6 +10,20 @@ pub enum Instruction {
     ///0nnn - SYS addr
     /// nnn - 12bit value
     SYS(u16),
+    ///00Cn - SCD nibble (SUPER-CHIP)
+    SCDn(u8),
+    ///00Dn - SCU nibble (XO-CHIP)
+    SCUn(u8),
+    ///00FB - SCR (SUPER-CHIP)
+    SCR,
+    ///00FC - SCL (SUPER-CHIP)
+    SCL,
+    ///00FD - EXIT (SUPER-CHIP)
+    EXIT,
+    ///00FE - LOW (SUPER-CHIP)
+    LOW,
+    ///00FF - HIGH (SUPER-CHIP)
+    HIGH,
 
     // STARTS with 1
     ///1nnn - JP addr
@@ -31,6 +45,10 @@ pub enum Instruction {
     // STARTS with 5
     ///5xy0 - SE Vx, Vy
     SExy(u8, u8),
+    ///5xy2 - SAVE Vx - Vy (XO-CHIP)
+    SAVExy(u8, u8),
+    ///5xy3 - LOAD Vx - Vy (XO-CHIP)
+    LOADxy(u8, u8),
 
     // STARTS with 6
     ///6xkk - LD Vx, byte
@@ -78,6 +96,7 @@ pub enum Instruction {
 
     // STARTS with D
     ///Dxyn - DRW Vx, Vy, nibble
+    /// n = 0 draws a 16x16 sprite (SUPER-CHIP)
     DRWxyn(u8, u8, u8),
 
     // STARTS with E
@@ -87,6 +106,13 @@ pub enum Instruction {
     SKPNPx(u8),
 
     // STARTS with F
+    ///F000 nnnn - LD I, long addr (XO-CHIP)
+    /// nnnn - 16bit value in the following word
+    LDILong,
+    ///Fn01 - PLANE n (XO-CHIP)
+    PLANEn(u8),
+    ///F002 - AUDIO (XO-CHIP)
+    AUDIO,
     ///Fx07 - LD Vx, DT
     LDxDt(u8),
     ///Fx0A - LD Vx, K
@@ -99,12 +125,20 @@ pub enum Instruction {
     ADDIx(u8),
     ///Fx29 - LD F, Vx
     LDFx(u8),
+    ///Fx30 - LD HF, Vx (SUPER-CHIP)
+    LDHFx(u8),
     ///Fx33 - LD B, Vx
     LDBx(u8),
+    ///Fx3A - PITCH Vx (XO-CHIP)
+    PITCHx(u8),
     ///Fx55 - LD [I], Vx
     LDIx(u8),
     ///Fx65 - LD Vx, [I]
     LDxI(u8),
+    ///Fx75 - LD R, Vx (SUPER-CHIP)
+    LDRx(u8),
+    ///Fx85 - LD Vx, R (SUPER-CHIP)
+    LDxR(u8),
 }
 
 pub struct Row {
@@ -167,6 +201,26 @@ impl Row {
             n: None,
         }
     }
+    fn reg(name: &'static str, reg: u8) -> Self {
+        Self {
+            name,
+            addr: None,
+            reg_left: Some(reg),
+            reg_right: None,
+            byte: None,
+            n: None,
+        }
+    }
+    fn n(name: &'static str, n: u8) -> Self {
+        Self {
+            name,
+            addr: None,
+            reg_left: None,
+            reg_right: None,
+            byte: None,
+            n: Some(n),
+        }
+    }
     fn name(name: &'static str) -> Self {
         Self {
             name,
@@ -177,6 +231,17 @@ impl Row {
             n: None,
         }
     }
+    /// a byte of sprite or other data
+    pub fn data(byte: u8) -> Self {
+        Self {
+            name: "DB",
+            addr: None,
+            reg_left: None,
+            reg_right: None,
+            byte: Some(byte),
+            n: None,
+        }
+    }
     pub fn empty() -> Self {
         Self {
             name: "",
@@ -217,11 +282,20 @@ impl std::fmt::Display for Instruction {
             Instruction::CLS => Row::name("CLS"),
             Instruction::RET => Row::name("RET"),
             Instruction::SYS(x) => Row::addr("SYS", x),
+            Instruction::SCDn(n) => Row::n("SCD", n),
+            Instruction::SCUn(n) => Row::n("SCU", n),
+            Instruction::SCR => Row::name("SCR"),
+            Instruction::SCL => Row::name("SCL"),
+            Instruction::EXIT => Row::name("EXIT"),
+            Instruction::LOW => Row::name("LOW"),
+            Instruction::HIGH => Row::name("HIGH"),
             Instruction::JPAddr(x) => Row::addr("JP", x),
             Instruction::CALLAddr(x) => Row::addr("CALL", x),
             Instruction::SExByte(x, b) => Row::reg_byte("SE", x, b),
             Instruction::SNExByte(x, b) => Row::reg_byte("SNE", x, b),
             Instruction::SExy(x, y) => Row::reg_reg("SE", x, y),
+            Instruction::SAVExy(x, y) => Row::reg_reg("SAVE", x, y),
+            Instruction::LOADxy(x, y) => Row::reg_reg("LOAD", x, y),
             Instruction::LDxByte(x, b) => Row::reg_byte("LD", x, b),
             Instruction::ADDxByte(x, b) => Row::reg_byte("ADD", x, b),
             Instruction::LDxy(x, y) => Row::reg_reg("LD", x, y),
@@ -238,28 +312,96 @@ impl std::fmt::Display for Instruction {
             Instruction::JPV0Addr(x) => Row::addr_reg("JP", x, 0),
             Instruction::RNDxByte(x, b) => Row::reg_byte("RND", x, b),
             Instruction::DRWxyn(x, y, n) => Row::reg_reg_n("DRW", x, y, n),
-            Instruction::SKPx(_) => todo!(),
-            Instruction::SKPNPx(_) => todo!(),
-            Instruction::LDxDt(_) => todo!(),
-            Instruction::LDxK(_) => todo!(),
-            Instruction::LDDTx(_) => todo!(),
-            Instruction::LDSTx(_) => todo!(),
-            Instruction::ADDIx(_) => todo!(),
-            Instruction::LDFx(_) => todo!(),
-            Instruction::LDBx(_) => todo!(),
-            Instruction::LDIx(_) => todo!(),
-            Instruction::LDxI(_) => todo!(),
+            Instruction::SKPx(x) => Row::reg("SKP", x),
+            Instruction::SKPNPx(x) => Row::reg("SKNP", x),
+            Instruction::LDxDt(x) => Row::reg("LDxDT", x),
+            Instruction::LDxK(x) => Row::reg("LDxK", x),
+            Instruction::LDDTx(x) => Row::reg("LDDT", x),
+            Instruction::LDSTx(x) => Row::reg("LDST", x),
+            Instruction::ADDIx(x) => Row::reg("ADDI", x),
+            Instruction::LDFx(x) => Row::reg("LDF", x),
+            Instruction::LDBx(x) => Row::reg("LDB", x),
+            Instruction::LDIx(x) => Row::reg("LDIx", x),
+            Instruction::LDxI(x) => Row::reg("LDxI", x),
+            Instruction::LDHFx(x) => Row::reg("LDHF", x),
+            Instruction::LDILong => Row::name("LDIL"),
+            Instruction::PLANEn(n) => Row::n("PLANE", n),
+            Instruction::AUDIO => Row::name("AUDIO"),
+            Instruction::PITCHx(x) => Row::reg("PITCH", x),
+            Instruction::LDRx(x) => Row::reg("LDR", x),
+            Instruction::LDxR(x) => Row::reg("LDxR", x),
         };
         write!(f, "{r}")
     }
 }
 
+impl Instruction {
+    /// assembler mnemonic and operands, in the syntax `asm::assemble` reads
+    ///
+    /// `LD I, LONG` is missing its address, which is the following word.
+    pub fn asm(&self) -> String {
+        match *self {
+            Instruction::CLS => "CLS".into(),
+            Instruction::RET => "RET".into(),
+            Instruction::SYS(a) => format!("SYS 0x{a:03X}"),
+            Instruction::SCDn(n) => format!("SCD {n}"),
+            Instruction::SCUn(n) => format!("SCU {n}"),
+            Instruction::SCR => "SCR".into(),
+            Instruction::SCL => "SCL".into(),
+            Instruction::EXIT => "EXIT".into(),
+            Instruction::LOW => "LOW".into(),
+            Instruction::HIGH => "HIGH".into(),
+            Instruction::JPAddr(a) => format!("JP 0x{a:03X}"),
+            Instruction::CALLAddr(a) => format!("CALL 0x{a:03X}"),
+            Instruction::SExByte(x, b) => format!("SE V{x:X}, 0x{b:02X}"),
+            Instruction::SNExByte(x, b) => format!("SNE V{x:X}, 0x{b:02X}"),
+            Instruction::SExy(x, y) => format!("SE V{x:X}, V{y:X}"),
+            Instruction::SAVExy(x, y) => format!("SAVE V{x:X}, V{y:X}"),
+            Instruction::LOADxy(x, y) => format!("LOAD V{x:X}, V{y:X}"),
+            Instruction::LDxByte(x, b) => format!("LD V{x:X}, 0x{b:02X}"),
+            Instruction::ADDxByte(x, b) => format!("ADD V{x:X}, 0x{b:02X}"),
+            Instruction::LDxy(x, y) => format!("LD V{x:X}, V{y:X}"),
+            Instruction::ORxy(x, y) => format!("OR V{x:X}, V{y:X}"),
+            Instruction::ANDxy(x, y) => format!("AND V{x:X}, V{y:X}"),
+            Instruction::XORxy(x, y) => format!("XOR V{x:X}, V{y:X}"),
+            Instruction::ADDxy(x, y) => format!("ADD V{x:X}, V{y:X}"),
+            Instruction::SUBxy(x, y) => format!("SUB V{x:X}, V{y:X}"),
+            Instruction::SHRxy(x, y) => format!("SHR V{x:X}, V{y:X}"),
+            Instruction::SUBNxy(x, y) => format!("SUBN V{x:X}, V{y:X}"),
+            Instruction::SHLxy(x, y) => format!("SHL V{x:X}, V{y:X}"),
+            Instruction::SNExy(x, y) => format!("SNE V{x:X}, V{y:X}"),
+            Instruction::LDIAddr(a) => format!("LD I, 0x{a:03X}"),
+            Instruction::JPV0Addr(a) => format!("JP V0, 0x{a:03X}"),
+            Instruction::RNDxByte(x, b) => format!("RND V{x:X}, 0x{b:02X}"),
+            Instruction::DRWxyn(x, y, n) => format!("DRW V{x:X}, V{y:X}, {n}"),
+            Instruction::SKPx(x) => format!("SKP V{x:X}"),
+            Instruction::SKPNPx(x) => format!("SKNP V{x:X}"),
+            Instruction::LDILong => "LD I, LONG".into(),
+            Instruction::PLANEn(n) => format!("PLANE {n}"),
+            Instruction::AUDIO => "AUDIO".into(),
+            Instruction::LDxDt(x) => format!("LD V{x:X}, DT"),
+            Instruction::LDxK(x) => format!("LD V{x:X}, K"),
+            Instruction::LDDTx(x) => format!("LD DT, V{x:X}"),
+            Instruction::LDSTx(x) => format!("LD ST, V{x:X}"),
+            Instruction::ADDIx(x) => format!("ADD I, V{x:X}"),
+            Instruction::LDFx(x) => format!("LD F, V{x:X}"),
+            Instruction::LDHFx(x) => format!("LD HF, V{x:X}"),
+            Instruction::LDBx(x) => format!("LD B, V{x:X}"),
+            Instruction::PITCHx(x) => format!("PITCH V{x:X}"),
+            Instruction::LDIx(x) => format!("LD [I], V{x:X}"),
+            Instruction::LDxI(x) => format!("LD V{x:X}, [I]"),
+            Instruction::LDRx(x) => format!("LD R, V{x:X}"),
+            Instruction::LDxR(x) => format!("LD V{x:X}, R"),
+        }
+    }
+}
+
 fn nibbles(v: u16) -> (u8, u8, u8, u8) {
     (
         ((v & 0xF000) >> 12) as u8,
         ((v & 0x0F00) >> 8) as u8,
         ((v & 0x00F0) >> 4) as u8,
-        ((v & 0x000F) >> 0) as u8,
+        (v & 0x000F) as u8,
     )
 }
 
@@ -270,16 +412,28 @@ impl Instruction {
         let addr = v & 0x0FFF;
         let kk = (v & 0x00FF) as u8;
         Ok(match i {
-            0x0 => match kk {
-                0xE0 => Instruction::CLS,
-                0xEE => Instruction::RET,
+            0x0 => match addr {
+                0x0E0 => Instruction::CLS,
+                0x0EE => Instruction::RET,
+                0x0C0..=0x0CF => Instruction::SCDn(n),
+                0x0D0..=0x0DF => Instruction::SCUn(n),
+                0x0FB => Instruction::SCR,
+                0x0FC => Instruction::SCL,
+                0x0FD => Instruction::EXIT,
+                0x0FE => Instruction::LOW,
+                0x0FF => Instruction::HIGH,
                 _ => Instruction::SYS(addr),
             },
             0x1 => Instruction::JPAddr(addr),
             0x2 => Instruction::CALLAddr(addr),
             0x3 => Instruction::SExByte(x, kk),
             0x4 => Instruction::SNExByte(x, kk),
-            0x5 => Instruction::SExy(x, y),
+            0x5 => match n {
+                0x0 => Instruction::SExy(x, y),
+                0x2 => Instruction::SAVExy(x, y),
+                0x3 => Instruction::LOADxy(x, y),
+                _ => bail!("invalid instruction {v}"),
+            },
             0x6 => Instruction::LDxByte(x, kk),
             0x7 => Instruction::ADDxByte(x, kk),
             0x8 => match n {
@@ -294,7 +448,10 @@ impl Instruction {
                 0xE => Instruction::SHLxy(x, y),
                 _ => bail!("invalid instruction {v}"),
             },
-            0x9 => Instruction::SNExy(x, y),
+            0x9 => match n {
+                0x0 => Instruction::SNExy(x, y),
+                _ => bail!("invalid instruction {v}"),
+            },
             0xA => Instruction::LDIAddr(addr),
             0xB => Instruction::JPV0Addr(addr),
             0xC => Instruction::RNDxByte(x, kk),
@@ -305,15 +462,22 @@ impl Instruction {
                 _ => bail!("invalid instruction {v}"),
             },
             0xF => match kk {
+                0x00 if x == 0 => Instruction::LDILong,
+                0x01 => Instruction::PLANEn(x),
+                0x02 if x == 0 => Instruction::AUDIO,
                 0x07 => Instruction::LDxDt(x),
                 0x0A => Instruction::LDxK(x),
                 0x15 => Instruction::LDDTx(x),
                 0x18 => Instruction::LDSTx(x),
                 0x1E => Instruction::ADDIx(x),
                 0x29 => Instruction::LDFx(x),
+                0x30 => Instruction::LDHFx(x),
                 0x33 => Instruction::LDBx(x),
+                0x3A => Instruction::PITCHx(x),
                 0x55 => Instruction::LDIx(x),
                 0x65 => Instruction::LDxI(x),
+                0x75 => Instruction::LDRx(x),
+                0x85 => Instruction::LDxR(x),
                 _ => bail!("invalid instruction {v}"),
             },
             _ => bail!("invalid instruction {v}"),
@@ -329,7 +493,7 @@ impl Instruction {
             (0x0F00 & ((*x as u16) << 8)) | *b as u16
         }
         fn xy(x: &u8, y: &u8) -> u16 {
-            (0x0F00 & ((*x as u16) << 8)) | (0xF0 & y) as u16
+            (0x0F00 & ((*x as u16) << 8)) | (0x00F0 & ((*y as u16) << 4))
         }
         fn xu16(x: &u8) -> u16 {
             0x0F00 & (*x as u16) << 8
@@ -337,7 +501,14 @@ impl Instruction {
         match self {
             Instruction::CLS => 0x00E0,
             Instruction::RET => 0x00EE,
-            Instruction::SYS(u) => 0x0111 & u,
+            Instruction::SYS(u) => addr(u),
+            Instruction::SCDn(n) => 0x00C0 | (0x000F & *n as u16),
+            Instruction::SCUn(n) => 0x00D0 | (0x000F & *n as u16),
+            Instruction::SCR => 0x00FB,
+            Instruction::SCL => 0x00FC,
+            Instruction::EXIT => 0x00FD,
+            Instruction::LOW => 0x00FE,
+            Instruction::HIGH => 0x00FF,
 
             Instruction::JPAddr(u) => addr(u) | 0x1000,
 
@@ -348,6 +519,8 @@ impl Instruction {
             Instruction::SNExByte(x, b) => x_byte(x, b) | 0x4000,
 
             Instruction::SExy(x, y) => xy(x, y) | 0x5000,
+            Instruction::SAVExy(x, y) => xy(x, y) | 0x5002,
+            Instruction::LOADxy(x, y) => xy(x, y) | 0x5003,
 
             Instruction::LDxByte(x, b) => x_byte(x, b) | 0x6000,
 
@@ -371,22 +544,128 @@ impl Instruction {
 
             Instruction::RNDxByte(x, b) => x_byte(x, b) | 0xC000,
 
-            Instruction::DRWxyn(x, y, n) => {
-                (0x0100 & *x as u16) | (0x0010 & *y as u16) | (0x0001 & *n as u16) | 0xD000
-            }
+            Instruction::DRWxyn(x, y, n) => xy(x, y) | (0x000F & *n as u16) | 0xD000,
 
             Instruction::SKPx(x) => xu16(x) | 0xE09E,
             Instruction::SKPNPx(x) => xu16(x) | 0xE0A1,
 
+            Instruction::LDILong => 0xF000,
+            Instruction::PLANEn(n) => xu16(n) | 0xF001,
+            Instruction::AUDIO => 0xF002,
             Instruction::LDxDt(x) => xu16(x) | 0xF007,
             Instruction::LDxK(x) => xu16(x) | 0xF00A,
             Instruction::LDDTx(x) => xu16(x) | 0xF015,
             Instruction::LDSTx(x) => xu16(x) | 0xF018,
             Instruction::ADDIx(x) => xu16(x) | 0xF01E,
             Instruction::LDFx(x) => xu16(x) | 0xF029,
+            Instruction::LDHFx(x) => xu16(x) | 0xF030,
             Instruction::LDBx(x) => xu16(x) | 0xF033,
+            Instruction::PITCHx(x) => xu16(x) | 0xF03A,
             Instruction::LDIx(x) => xu16(x) | 0xF055,
             Instruction::LDxI(x) => xu16(x) | 0xF065,
+            Instruction::LDRx(x) => xu16(x) | 0xF075,
+            Instruction::LDxR(x) => xu16(x) | 0xF085,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+
+    use super::*;
+
+    #[test]
+    fn every_valid_word_encodes_back() {
+        let mut valid = 0;
+        for w in 0..=0xFFFFu16 {
+            if let Ok(i) = Instruction::decode(w) {
+                assert_eq!(i.encode(), w, "{w:04X} decoded to {i:?}");
+                valid += 1;
+            }
+        }
+        // 11 whole prefixes, 0x5, 0x8 and 0x9 by their low nibble, 0xE and 0xF by byte
+        assert_eq!(
+            valid,
+            11 * 0x1000 + 3 * 0x100 + 9 * 0x100 + 0x100 + 2 * 16 + 226
+        );
+    }
+
+    /// any instruction `decode` can produce, operands within their nibbles
+    fn instruction() -> impl Strategy<Value = Instruction> {
+        use Instruction::*;
+        let addr = || 0..0x1000u16;
+        let reg = || 0..16u8;
+        let byte = any::<u8>;
+        let sys = addr().prop_filter("collides with another 0x0 op", |a| {
+            matches!(Instruction::decode(*a), Ok(SYS(_)))
+        });
+        prop_oneof![
+            Just(CLS),
+            Just(RET),
+            sys.prop_map(SYS),
+            reg().prop_map(SCDn),
+            reg().prop_map(SCUn),
+            Just(SCR),
+            Just(SCL),
+            Just(EXIT),
+            Just(LOW),
+            Just(HIGH),
+            addr().prop_map(JPAddr),
+            addr().prop_map(CALLAddr),
+            (reg(), byte()).prop_map(|(x, b)| SExByte(x, b)),
+            (reg(), byte()).prop_map(|(x, b)| SNExByte(x, b)),
+            (reg(), reg()).prop_map(|(x, y)| SExy(x, y)),
+            (reg(), reg()).prop_map(|(x, y)| SAVExy(x, y)),
+            (reg(), reg()).prop_map(|(x, y)| LOADxy(x, y)),
+            (reg(), byte()).prop_map(|(x, b)| LDxByte(x, b)),
+            (reg(), byte()).prop_map(|(x, b)| ADDxByte(x, b)),
+            (reg(), reg()).prop_map(|(x, y)| LDxy(x, y)),
+            (reg(), reg()).prop_map(|(x, y)| ORxy(x, y)),
+            (reg(), reg()).prop_map(|(x, y)| ANDxy(x, y)),
+            (reg(), reg()).prop_map(|(x, y)| XORxy(x, y)),
+            (reg(), reg()).prop_map(|(x, y)| ADDxy(x, y)),
+            (reg(), reg()).prop_map(|(x, y)| SUBxy(x, y)),
+            (reg(), reg()).prop_map(|(x, y)| SHRxy(x, y)),
+            (reg(), reg()).prop_map(|(x, y)| SUBNxy(x, y)),
+            (reg(), reg()).prop_map(|(x, y)| SHLxy(x, y)),
+            (reg(), reg()).prop_map(|(x, y)| SNExy(x, y)),
+            addr().prop_map(LDIAddr),
+            addr().prop_map(JPV0Addr),
+            (reg(), byte()).prop_map(|(x, b)| RNDxByte(x, b)),
+            (reg(), reg(), reg()).prop_map(|(x, y, n)| DRWxyn(x, y, n)),
+            reg().prop_map(SKPx),
+            reg().prop_map(SKPNPx),
+            Just(LDILong),
+            reg().prop_map(PLANEn),
+            Just(AUDIO),
+            reg().prop_map(LDxDt),
+            reg().prop_map(LDxK),
+            reg().prop_map(LDDTx),
+            reg().prop_map(LDSTx),
+            reg().prop_map(ADDIx),
+            reg().prop_map(LDFx),
+            reg().prop_map(LDHFx),
+            reg().prop_map(LDBx),
+            reg().prop_map(PITCHx),
+            reg().prop_map(LDIx),
+            reg().prop_map(LDxI),
+            reg().prop_map(LDRx),
+            reg().prop_map(LDxR),
+        ]
+    }
+
+    proptest! {
+        #[test]
+        fn encoded_instructions_decode_back(i in instruction()) {
+            prop_assert_eq!(Instruction::decode(i.encode()).unwrap(), i);
+        }
+
+        #[test]
+        fn operands_land_in_their_nibbles(x in 0..16u8, y in 0..16u8, n in 0..16u8) {
+            let w = Instruction::DRWxyn(x, y, n).encode();
+            prop_assert_eq!(nibbles(w), (0xD, x, y, n));
+            prop_assert_eq!(nibbles(Instruction::SUBxy(x, y).encode()), (0x8, x, y, 0x5));
         }
     }
 }
@@ -0,0 +1,248 @@
+use std::{
+    io::{Read, Write},
+    path::Path,
+};
+
+use color_eyre::{eyre::bail, Result};
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+use super::{quirks::Quirks, rng::Rng, Processor};
+
+/// first bytes of every movie file
+pub const MOVIE_MAGIC: [u8; 4] = *b"C8MV";
+/// version written by `Movie::save`
+pub const MOVIE_VERSION: u16 = 2;
+
+/// Movie
+///
+/// the keypad of every frame of a run from power on, with the random
+/// source and settings it needs to play out the same again. recorded with `--record`
+/// and replayed with `--play`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Movie {
+    /// `RNDxByte` source at power on
+    pub rng: Rng,
+    pub ips: u32,
+    pub quirks: Quirks,
+    /// hash of memory at power on, tells the rom and platform apart
+    pub fingerprint: u64,
+    /// pressed keys of each frame, bit n for key n
+    pub frames: Vec<u16>,
+}
+
+impl Movie {
+    /// empty movie of `proc`, which has not run any ops yet
+    pub fn new(proc: &Processor, ips: u32) -> Self {
+        Self {
+            rng: proc.rng().clone(),
+            ips,
+            quirks: *proc.quirks(),
+            fingerprint: fingerprint(proc.memory()),
+            frames: vec![],
+        }
+    }
+
+    /// add the keys held for the frame about to run
+    pub fn record(&mut self, proc: &Processor) {
+        let keys = proc.keys().iter().rev().fold(0, |m, &k| m << 1 | k as u16);
+        self.frames.push(keys);
+    }
+
+    /// drop the last frame, it was rewound over. the frames recorded after
+    /// it only replay the same if the clock phase was rewound too
+    pub fn unrecord(&mut self) {
+        self.frames.pop();
+    }
+
+    /// write the magic, version and movie
+    pub fn save(&self, mut w: impl Write) -> Result<()> {
+        w.write_all(&MOVIE_MAGIC)?;
+        w.write_all(&MOVIE_VERSION.to_le_bytes())?;
+        bincode::serialize_into(w, self)?;
+        Ok(())
+    }
+
+    pub fn load(mut r: impl Read) -> Result<Self> {
+        let mut header = [0; 6];
+        r.read_exact(&mut header)?;
+        if header[..4] != MOVIE_MAGIC {
+            bail!("not a chiprs movie");
+        }
+        match u16::from_le_bytes([header[4], header[5]]) {
+            1 => Ok(bincode::deserialize_from::<_, MovieV1>(r)?.into()),
+            2 => Ok(bincode::deserialize_from(r)?),
+            v => bail!("movie version {v} is not supported, expected {MOVIE_VERSION}"),
+        }
+    }
+
+    pub fn save_to_path(&self, path: impl AsRef<Path>) -> Result<()> {
+        let mut buf = vec![];
+        self.save(&mut buf)?;
+        std::fs::write(path, buf)?;
+        Ok(())
+    }
+
+    pub fn load_from_path(path: impl AsRef<Path>) -> Result<Self> {
+        Self::load(std::fs::read(path)?.as_slice())
+    }
+}
+
+/// version 1 layout, seeding a splitmix
+#[derive(Deserialize)]
+struct MovieV1 {
+    seed: u64,
+    ips: u32,
+    quirks: Quirks,
+    fingerprint: u64,
+    frames: Vec<u16>,
+}
+
+impl From<MovieV1> for Movie {
+    fn from(m: MovieV1) -> Self {
+        Movie {
+            rng: Rng::seeded(m.seed),
+            ips: m.ips,
+            quirks: m.quirks,
+            fingerprint: m.fingerprint,
+            frames: m.frames,
+        }
+    }
+}
+
+/// FNV-1a hash of `memory`
+fn fingerprint(memory: &[u8]) -> u64 {
+    memory.iter().fold(0xcbf2_9ce4_8422_2325, |h, &b| {
+        (h ^ b as u64).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+/// Playback
+///
+/// a movie being replayed into a processor, a frame at a time.
+#[derive(Debug)]
+pub struct Playback {
+    movie: Movie,
+    frame: usize,
+}
+
+impl Playback {
+    /// replay `movie` on `proc` from power on, taking over its rng and quirks
+    pub fn start(movie: Movie, proc: &mut Processor) -> Self {
+        if fingerprint(proc.memory()) != movie.fingerprint {
+            warn!("movie was recorded on another rom or platform, it will likely desync");
+        }
+        proc.set_rng(movie.rng.clone());
+        proc.set_quirks(movie.quirks);
+        Self { movie, frame: 0 }
+    }
+
+    /// instructions per second the movie was recorded at
+    pub fn ips(&self) -> u32 {
+        self.movie.ips
+    }
+
+    /// frames played so far
+    pub fn frame(&self) -> usize {
+        self.frame
+    }
+
+    /// hold the keys of the next frame, false once the movie has run out
+    pub fn next(&mut self, proc: &mut Processor) -> bool {
+        let Some(keys) = self.movie.frames.get(self.frame) else {
+            return false;
+        };
+        for k in 0..16 {
+            proc.set_key(k, keys >> k & 1 == 1);
+        }
+        self.frame += 1;
+        true
+    }
+
+    /// step back a frame, it was rewound over
+    pub fn back(&mut self) {
+        self.frame = self.frame.saturating_sub(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::{asm::assemble, clock::Clock, processor::RendererState, Rom};
+
+    fn rom() -> Rom {
+        assemble(
+            "       LD V0, 5
+            loop:   RND V1, 0xFF
+                    SKNP V0
+                    XOR V2, V1
+                    JP loop",
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn replays_keys_and_random_numbers() {
+        let mut proc = Processor::with_rom(rom());
+        // a fixed seed, a random one XORs V2 back to 0 one run in 256
+        proc.set_rng(Rng::seeded(1));
+        let mut clock = Clock::new(600);
+        let mut movie = Movie::new(&proc, 600);
+        for frame in 0..30 {
+            proc.set_key(5, (10..20).contains(&frame));
+            movie.record(&proc);
+            clock.frame(&mut proc).unwrap();
+        }
+        assert_eq!(movie.frames[10], 1 << 5);
+        let mut buf = vec![];
+        movie.save(&mut buf).unwrap();
+
+        let mut replay = Processor::with_rom(rom());
+        let mut playback = Playback::start(Movie::load(buf.as_slice()).unwrap(), &mut replay);
+        let mut clock = Clock::new(playback.ips());
+        while playback.next(&mut replay) {
+            clock.frame(&mut replay).unwrap();
+        }
+        assert_eq!(playback.frame(), 30);
+        assert_eq!(replay.snapshot(), proc.snapshot());
+        assert_ne!(replay.registers()[2], 0);
+    }
+
+    #[test]
+    fn rewinding_while_recording_replays_the_same() {
+        let mut proc = Processor::with_rom(rom());
+        let mut state = RendererState {
+            clock: Clock::new(700),
+            recording: Some(Movie::new(&proc, 700)),
+            ..Default::default()
+        };
+        for frame in 0..15 {
+            proc.set_key(5, (3..12).contains(&frame));
+            state.frame(&mut proc).unwrap();
+            if frame == 9 {
+                assert!(state.back(&mut proc).unwrap());
+            }
+        }
+        let movie = state.recording.take().unwrap();
+        assert_eq!(movie.frames.len(), 14);
+
+        let mut replay = Processor::with_rom(rom());
+        let mut playback = Playback::start(movie, &mut replay);
+        let mut clock = Clock::new(playback.ips());
+        while playback.next(&mut replay) {
+            clock.frame(&mut replay).unwrap();
+        }
+        assert_eq!(replay.snapshot(), proc.snapshot());
+    }
+
+    #[test]
+    fn rejects_other_files() {
+        assert!(Movie::load(&b"C8ST\x02\0"[..]).is_err());
+        let mut buf = vec![];
+        Movie::new(&Processor::default(), 700)
+            .save(&mut buf)
+            .unwrap();
+        buf[4] = 9;
+        assert!(Movie::load(buf.as_slice()).is_err());
+    }
+}
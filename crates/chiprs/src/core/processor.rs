@@ -1,46 +1,180 @@
-use super::{framebuffer::FrameBuffer, instructions::Instruction, rom::Rom};
-
-use color_eyre::{
-    eyre::{bail, ContextCompat},
-    Result,
+use super::{
+    beeper::Beeper,
+    clock::Clock,
+    framebuffer::FrameBuffer,
+    instructions::Instruction,
+    movie::{Movie, Playback},
+    quirks::{MemoryIncrement, Platform, Quirks},
+    rewind::Rewind,
+    rng::Rng,
+    rom::Rom,
+    state::Snapshot,
 };
 
+use std::{path::PathBuf, time::Duration};
+
+use color_eyre::{eyre::bail, Result};
+use input_core::{Bindings, InputSource};
+use tracing::info;
+
+/// address the hex font is loaded at, in the interpreter area
+pub const FONT_ADDR: u16 = 0x050;
+/// address the SUPER-CHIP 8x10 font is loaded at, right after the hex font
+pub const BIG_FONT_ADDR: u16 = 0x0A0;
+
+/// size of the classic chip8 address space
+pub const MEMORY_SIZE: usize = 0x1000;
+/// size of the XO-CHIP address space
+pub const XO_CHIP_MEMORY_SIZE: usize = 0x10000;
+
+/// Memory
+///
+/// 4 KiB address space, or 64 KiB for XO-CHIP.
 #[derive(Debug)]
-pub struct Memory([u8; 4096]);
+pub struct Memory(Vec<u8>);
 
 impl Memory {
-    fn get_slice<'a>(&'a self, idx: u16, n: u16) -> &'a [u8] {
-        &self.0[(idx as usize)..((idx + n) as usize)]
+    fn new(size: usize) -> Self {
+        Self(vec![0; size])
+    }
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+    /// `n` bytes from `idx`, wrapping round the end of memory like the
+    /// address bus does
+    fn read(&self, idx: u16, n: u16) -> Vec<u8> {
+        (0..n as usize)
+            .map(|k| self.0[(idx as usize + k) % self.0.len()])
+            .collect()
+    }
+    /// store `bytes` from `idx`, wrapping round the end of memory
+    fn write(&mut self, idx: u16, bytes: &[u8]) {
+        let len = self.0.len();
+        for (k, b) in bytes.iter().enumerate() {
+            self.0[(idx as usize + k) % len] = *b;
+        }
+    }
+    fn load_font(&mut self) {
+        let font = "0123456789abcdef".chars().flat_map(Processor::text);
+        for (b, f) in self.0[FONT_ADDR as usize..].iter_mut().zip(font) {
+            *b = f;
+        }
+        let font = "0123456789abcdef".chars().flat_map(Processor::big_text);
+        for (b, f) in self.0[BIG_FONT_ADDR as usize..].iter_mut().zip(font) {
+            *b = f;
+        }
     }
     fn load(&mut self, r: Rom) -> u16 {
         match r {
             Rom::Base(m, _) => {
-                let s = &mut self.0[0x200..];
+                let s = &mut self.0[0x200..0x200 + m.len()];
                 s.copy_from_slice(&m);
                 0x200
             }
             Rom::ETI600(m, _) => {
-                let s = &mut self.0[0x600..];
+                let s = &mut self.0[0x600..0x600 + m.len()];
                 s.copy_from_slice(&m);
                 0x600
             }
+            Rom::XOChip(m) => {
+                let s = &mut self.0[0x200..0x200 + m.len()];
+                s.copy_from_slice(&m);
+                0x200
+            }
         }
     }
 
-    fn get(&self, i: usize) -> Option<u16> {
-        Some(((self.0[i] as u16) << 8) + self.0[i + 1] as u16)
+    /// big endian word at `idx`, the last byte pairs with the first
+    fn word(&self, idx: u16) -> u16 {
+        let len = self.0.len();
+        let idx = idx as usize;
+        u16::from_be_bytes([self.0[idx % len], self.0[(idx + 1) % len]])
     }
 }
 
 impl Default for Memory {
     fn default() -> Self {
-        Self([0; 4096])
+        Self::new(MEMORY_SIZE)
     }
 }
 
 #[derive(Debug, Default)]
 pub struct RendererState {
     pub instant: Option<std::time::Instant>,
+    pub clock: Clock,
+    pub rewind: Rewind,
+    /// the rewind key is held, frames run backwards
+    pub rewinding: bool,
+    /// gamepads and scripts, polled before every frame
+    pub inputs: Vec<Box<dyn InputSource>>,
+    /// keypad keys the input buttons press
+    pub bindings: Bindings<u8>,
+    /// movie the keypad of every frame is recorded into
+    pub recording: Option<Movie>,
+    /// movie whose keypad replaces live input until it runs out
+    pub playback: Option<Playback>,
+    /// sound of every frame run forwards, none when muted
+    pub beeper: Option<Beeper>,
+}
+
+impl RendererState {
+    /// run the frames `dt` of wall time is worth, forwards while recording
+    /// them for rewind, or backwards while the rewind key is held
+    pub fn run(&mut self, proc: &mut Processor, dt: Duration) -> Result<()> {
+        for _ in 0..self.clock.frames_due(dt) {
+            if self.rewinding {
+                if !self.back(proc)? {
+                    break;
+                }
+            } else {
+                self.frame(proc)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// step back one frame, false once the oldest frame kept is reached
+    pub fn back(&mut self, proc: &mut Processor) -> Result<bool> {
+        let Some((s, cycle_remainder)) = self.rewind.rewind()? else {
+            return Ok(false);
+        };
+        proc.restore(s);
+        // the clock's phase decides how many ops the next frames run
+        self.clock.set_cycle_remainder(cycle_remainder);
+        if let Some(movie) = &mut self.recording {
+            movie.unrecord();
+        }
+        if let Some(playback) = &mut self.playback {
+            playback.back();
+        }
+        Ok(true)
+    }
+
+    /// run one frame forwards, independent of wall time
+    pub fn frame(&mut self, proc: &mut Processor) -> Result<()> {
+        for input in &mut self.inputs {
+            let events = input.poll()?;
+            self.bindings.apply(&events, |k, p| proc.set_key(k, p));
+        }
+        if let Some(playback) = &mut self.playback {
+            if !playback.next(proc) {
+                info!("movie finished, input is live again");
+                self.playback = None;
+            }
+        }
+        if let Some(movie) = &mut self.recording {
+            movie.record(proc);
+        }
+        self.clock.frame(proc)?;
+        if let Some(beeper) = &mut self.beeper {
+            beeper.frame(proc)?;
+        }
+        self.rewind
+            .push(&proc.snapshot(), self.clock.cycle_remainder())
+    }
 }
 
 #[derive(Default, Debug)]
@@ -54,18 +188,230 @@ pub struct Processor {
     stack_pointer: u8,
     stack: [u16; 16],
     memory: Memory,
-    pub framebuffer: Option<FrameBuffer>,
+    framebuffer: FrameBuffer,
+    /// pressed state of the 16 hex keys
+    keys: [bool; 16],
+    /// key pressed while blocked on `LD Vx, K`, stored once released
+    key_wait: Option<u8>,
+    quirks: Quirks,
+    /// a sprite was drawn since the last timer tick, see `Quirks::display_wait`
+    drawn: bool,
+    /// the sound timer ran through the last frame, left out of snapshots
+    /// as the next tick sets it again
+    beeping: bool,
+    /// set by the SUPER-CHIP `EXIT` op, no further ops are run
+    halted: bool,
+    /// SUPER-CHIP RPL user flags
+    rpl: [u8; 16],
+    /// file the RPL flags are persisted to
+    rpl_path: Option<PathBuf>,
+    /// XO-CHIP 1-bit audio pattern, 128 samples
+    audio_pattern: [u8; 16],
+    /// XO-CHIP pitch register, playback rate is 4000*2^((pitch-64)/48) hz
+    pitch: u8,
+    /// source of `RNDxByte`, a randomly seeded splitmix unless `set_rng` is called
+    rng: Rng,
 }
 
 impl Processor {
     pub fn with_rom(rom: Rom) -> Processor {
-        let mut proc = Processor::default();
+        Self::with_platform(rom, Platform::default())
+    }
+
+    /// processor with the quirks and address space of `platform`
+    ///
+    /// XO-CHIP, or any rom too large for 4 KiB, gets the 64 KiB address space.
+    pub fn with_platform(rom: Rom, platform: Platform) -> Processor {
+        let mut proc = Processor {
+            pitch: 64,
+            rng: Rng::seeded(rand::random()),
+            ..Default::default()
+        };
+        if platform == Platform::XoChip || matches!(rom, Rom::XOChip(_)) {
+            proc.memory = Memory::new(XO_CHIP_MEMORY_SIZE);
+        }
+        proc.set_quirks(platform.into());
+        proc.memory.load_font();
         proc.program_counter = proc.memory.load(rom);
         proc
     }
 
+    pub fn quirks(&self) -> &Quirks {
+        &self.quirks
+    }
+
+    pub fn set_quirks(&mut self, quirks: Quirks) {
+        self.framebuffer.set_edge_mode(quirks.edge_mode());
+        self.quirks = quirks;
+    }
+
+    /// persist the RPL flags to `path`, loading any flags already saved there
+    pub fn set_rpl_path(&mut self, path: impl Into<PathBuf>) -> Result<()> {
+        let path = path.into();
+        if path.exists() {
+            let saved = std::fs::read(&path)?;
+            let n = saved.len().min(self.rpl.len());
+            self.rpl[..n].copy_from_slice(&saved[..n]);
+        }
+        self.rpl_path = Some(path);
+        Ok(())
+    }
+
+    pub fn rpl(&self) -> &[u8; 16] {
+        &self.rpl
+    }
+
+    pub fn audio_pattern(&self) -> &[u8; 16] {
+        &self.audio_pattern
+    }
+
+    pub fn pitch(&self) -> u8 {
+        self.pitch
+    }
+
+    /// sample rate the XO-CHIP audio pattern is played back at
+    pub fn pattern_rate(&self) -> f64 {
+        4000.0 * 2f64.powf((self.pitch as f64 - 64.0) / 48.0)
+    }
+
+    /// source of `RNDxByte`, in its current state
+    pub fn rng(&self) -> &Rng {
+        &self.rng
+    }
+
+    /// draw `RNDxByte` from `rng` from now on
+    pub fn set_rng(&mut self, rng: Rng) {
+        self.rng = rng;
+    }
+
+    /// the rom ran `EXIT`
+    pub fn halted(&self) -> bool {
+        self.halted
+    }
+
+    /// the display bit-plane, 64x32 or 128x64 in hires mode
+    pub fn framebuffer(&self) -> &FrameBuffer {
+        &self.framebuffer
+    }
+
+    pub fn framebuffer_mut(&mut self) -> &mut FrameBuffer {
+        &mut self.framebuffer
+    }
+
+    pub fn registers(&self) -> &[u8; 16] {
+        &self.registers
+    }
+
+    pub fn i(&self) -> u16 {
+        self.i
+    }
+
+    pub fn program_counter(&self) -> u16 {
+        self.program_counter
+    }
+
+    pub fn stack_pointer(&self) -> u8 {
+        self.stack_pointer
+    }
+
+    /// return addresses of the active calls, outermost first
+    pub fn stack(&self) -> &[u16] {
+        &self.stack[..self.stack_pointer as usize]
+    }
+
+    /// the whole address space, interpreter area and fonts included
+    pub fn memory(&self) -> &[u8] {
+        &self.memory.0
+    }
+
+    pub fn delay_timer(&self) -> u8 {
+        self.delay_timer
+    }
+
+    pub fn sound_timer(&self) -> u8 {
+        self.sound_timer
+    }
+
+    /// the buzzer sounded over the last frame, the sound timer was still
+    /// counting when its timers ticked
+    pub fn beeping(&self) -> bool {
+        self.beeping
+    }
+
+    /// set the pressed state of hex key `key` (0x0..=0xF)
+    pub fn set_key(&mut self, key: u8, pressed: bool) {
+        self.keys[(key & 0xF) as usize] = pressed;
+    }
+
+    pub fn keys(&self) -> &[bool; 16] {
+        &self.keys
+    }
+
+    /// copy of the machine state, see `Snapshot`
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            registers: self.registers,
+            i: self.i,
+            delay_timer: self.delay_timer,
+            sound_timer: self.sound_timer,
+            program_counter: self.program_counter,
+            stack_pointer: self.stack_pointer,
+            stack: self.stack,
+            memory: self.memory.0.clone(),
+            framebuffer: self.framebuffer.clone(),
+            key_wait: self.key_wait,
+            quirks: self.quirks,
+            drawn: self.drawn,
+            halted: self.halted,
+            rpl: self.rpl,
+            audio_pattern: self.audio_pattern,
+            pitch: self.pitch,
+            rng: self.rng.clone(),
+        }
+    }
+
+    /// carry on from a snapshot, keeping the pressed keys and RPL path
+    pub fn restore(&mut self, s: Snapshot) {
+        self.registers = s.registers;
+        self.i = s.i;
+        self.delay_timer = s.delay_timer;
+        self.sound_timer = s.sound_timer;
+        self.program_counter = s.program_counter;
+        self.stack_pointer = s.stack_pointer;
+        self.stack = s.stack;
+        self.memory = Memory(s.memory);
+        self.framebuffer = s.framebuffer;
+        self.key_wait = s.key_wait;
+        self.quirks = s.quirks;
+        self.drawn = s.drawn;
+        self.halted = s.halted;
+        self.rpl = s.rpl;
+        self.audio_pattern = s.audio_pattern;
+        self.pitch = s.pitch;
+        self.rng = s.rng;
+    }
+
+    /// count both timers down by one, called at 60hz
+    pub fn tick_timers(&mut self) {
+        self.beeping = self.sound_timer > 0;
+        self.delay_timer = self.delay_timer.saturating_sub(1);
+        self.sound_timer = self.sound_timer.saturating_sub(1);
+        self.drawn = false;
+    }
+
+    /// run processor for `cycles` ops
+    pub fn run(&mut self, cycles: usize) -> Result<()> {
+        for _ in 0..cycles {
+            self.step()?;
+        }
+        Ok(())
+    }
+
     /// run processor for a single op
-    pub fn run(&mut self, window: &winit::window::Window, rs: &mut RendererState) -> Result<()> {
+    pub fn step(&mut self) -> Result<()> {
+        if self.halted {
+            return Ok(());
+        }
         // tracing::info!(
         //     regs = format!("{:?}", self.registers),
         //     mem_addr = self.i,
@@ -73,160 +419,279 @@ impl Processor {
         //     stack = format!("{:?}", self.stack),
         //     stack_pointer = self.stack_pointer,
         // );
-        if self.framebuffer.is_none() {
-            _ = self.framebuffer.insert(FrameBuffer::new(window)?);
-        }
-        self.framebuffer.as_mut().unwrap().clear();
-        // let Some(instant) = rs.instant.take() else {
-        //     bail!("time not present");
-        // };
-        // let dt = instant.elapsed();
-        // let frame_time = 1.0 / dt.as_secs_f64();
-        // println!("{frame_time}");
-        _ = rs.instant.insert(std::time::Instant::now());
-        if self.program_counter >= 4096 {
+        if self.program_counter as usize >= self.memory.len() {
             bail!("bad program counter value {}", self.program_counter);
         }
-        if let Some(x) = self.memory.get(self.program_counter as usize) {
-            let inst = Instruction::decode(x)?;
-            match inst {
-                Instruction::SYS(_) => todo!("ignored on modern interpreters"),
-                Instruction::CLS => self
-                    .framebuffer
-                    .as_mut()
-                    .context("window not initialized")?
-                    .clear(),
-                Instruction::RET => {
-                    // get from stack
-                    self.stack_pointer -= 1;
-                    self.program_counter = self.stack[self.stack_pointer as usize];
-                    return Ok(());
-                }
-                Instruction::JPAddr(addr) => {
-                    self.program_counter = addr;
-                    return Ok(());
+        let inst = Instruction::decode(self.memory.word(self.program_counter))?;
+        match inst {
+            // machine code routines of the original hardware, skipped
+            Instruction::SYS(_) => {}
+            Instruction::CLS => self.framebuffer.clear(),
+            Instruction::SCDn(n) => self.framebuffer.scroll_down(n as usize),
+            Instruction::SCUn(n) => self.framebuffer.scroll_up(n as usize),
+            Instruction::SCR => self.framebuffer.scroll_right(4),
+            Instruction::SCL => self.framebuffer.scroll_left(4),
+            Instruction::EXIT => {
+                self.halted = true;
+                return Ok(());
+            }
+            Instruction::LOW => self.framebuffer.set_hires(false),
+            Instruction::HIGH => self.framebuffer.set_hires(true),
+            Instruction::RET => {
+                // get from stack
+                if self.stack_pointer == 0 {
+                    bail!("return with an empty stack at {}", self.program_counter);
                 }
-                Instruction::CALLAddr(addr) => {
-                    self.stack[self.stack_pointer as usize] = self.program_counter;
-                    self.stack_pointer += 1;
-                    self.program_counter = addr;
-                    return Ok(());
+                self.stack_pointer -= 1;
+                self.program_counter = self.stack[self.stack_pointer as usize];
+                return Ok(());
+            }
+            Instruction::JPAddr(addr) => {
+                self.program_counter = addr;
+                return Ok(());
+            }
+            Instruction::CALLAddr(addr) => {
+                if self.stack_pointer as usize == self.stack.len() {
+                    bail!("stack overflow at {}", self.program_counter);
                 }
-                Instruction::SExByte(x, b) => {
-                    if self.registers[x as usize] == b {
-                        self.program_counter += 2;
-                    }
+                // return to the op after the call
+                self.stack[self.stack_pointer as usize] = self.program_counter.wrapping_add(2);
+                self.stack_pointer += 1;
+                self.program_counter = addr;
+                return Ok(());
+            }
+            Instruction::SExByte(x, b) => {
+                if self.registers[x as usize] == b {
+                    self.skip_next();
                 }
-                Instruction::SNExByte(x, b) => {
-                    if self.registers[x as usize] == b {
-                        self.program_counter += 2;
-                    }
+            }
+            Instruction::SNExByte(x, b) => {
+                if self.registers[x as usize] != b {
+                    self.skip_next();
                 }
-                Instruction::SExy(x, y) => {
-                    if self.registers[x as usize] == self.registers[y as usize] {
-                        self.program_counter += 2;
-                    }
+            }
+            Instruction::SExy(x, y) => {
+                if self.registers[x as usize] == self.registers[y as usize] {
+                    self.skip_next();
                 }
-                Instruction::LDxByte(x, b) => {
-                    self.registers[x as usize] = b;
+            }
+            Instruction::SAVExy(x, y) => {
+                let regs = self.register_range(x, y);
+                for (n, r) in regs.enumerate() {
+                    self.memory
+                        .write(self.i.wrapping_add(n as u16), &[self.registers[r]]);
                 }
-                Instruction::ADDxByte(x, b) => {
-                    self.registers[x as usize] += b;
+            }
+            Instruction::LOADxy(x, y) => {
+                let regs = self.register_range(x, y);
+                for (n, r) in regs.enumerate() {
+                    self.registers[r] = self.memory.read(self.i.wrapping_add(n as u16), 1)[0];
                 }
-                Instruction::LDxy(x, y) => {
-                    self.registers[x as usize] = self.registers[y as usize];
+            }
+            Instruction::LDxByte(x, b) => {
+                self.registers[x as usize] = b;
+            }
+            Instruction::ADDxByte(x, b) => {
+                // no carry flag, VF is left alone
+                self.registers[x as usize] = self.registers[x as usize].wrapping_add(b);
+            }
+            Instruction::LDxy(x, y) => {
+                self.registers[x as usize] = self.registers[y as usize];
+            }
+            Instruction::ORxy(x, y) => {
+                self.registers[x as usize] |= self.registers[y as usize];
+                if self.quirks.vf_reset {
+                    self.registers[15] = 0;
                 }
-                Instruction::ORxy(x, y) => {
-                    self.registers[x as usize] =
-                        self.registers[x as usize] | self.registers[y as usize];
+            }
+            Instruction::ANDxy(x, y) => {
+                self.registers[x as usize] &= self.registers[y as usize];
+                if self.quirks.vf_reset {
+                    self.registers[15] = 0;
                 }
-                Instruction::ANDxy(x, y) => {
-                    self.registers[x as usize] =
-                        self.registers[x as usize] & self.registers[y as usize];
+            }
+            Instruction::XORxy(x, y) => {
+                self.registers[x as usize] ^= self.registers[y as usize];
+                if self.quirks.vf_reset {
+                    self.registers[15] = 0;
                 }
-                Instruction::XORxy(x, y) => {
-                    self.registers[x as usize] =
-                        self.registers[x as usize] ^ self.registers[y as usize];
+            }
+            // the flag is written last so it wins when VX is VF
+            Instruction::ADDxy(x, y) => {
+                let (v, carry) =
+                    self.registers[x as usize].overflowing_add(self.registers[y as usize]);
+                self.registers[x as usize] = v;
+                self.registers[15] = carry as u8;
+            }
+            Instruction::SUBxy(x, y) => {
+                let (v, borrow) =
+                    self.registers[x as usize].overflowing_sub(self.registers[y as usize]);
+                self.registers[x as usize] = v;
+                self.registers[15] = !borrow as u8;
+            }
+            Instruction::SHRxy(x, y) => {
+                let v = self.registers[if self.quirks.shift_vy { y } else { x } as usize];
+                self.registers[x as usize] = v >> 1;
+                self.registers[15] = v & 1;
+            }
+            Instruction::SUBNxy(x, y) => {
+                let (v, borrow) =
+                    self.registers[y as usize].overflowing_sub(self.registers[x as usize]);
+                self.registers[x as usize] = v;
+                self.registers[15] = !borrow as u8;
+            }
+            Instruction::SHLxy(x, y) => {
+                let v = self.registers[if self.quirks.shift_vy { y } else { x } as usize];
+                self.registers[x as usize] = v << 1;
+                self.registers[15] = (v & 0b10000000) >> 7;
+            }
+            Instruction::SNExy(x, y) => {
+                if self.registers[x as usize] != self.registers[y as usize] {
+                    self.skip_next();
                 }
-                Instruction::ADDxy(x, y) => {
-                    let s = self.registers[x as usize] as u16 + self.registers[y as usize] as u16;
-                    if s > 0xFF {
-                        self.registers[15] = 1;
-                    } else {
-                        self.registers[15] = 0;
-                    }
-                    self.registers[x as usize] = (s & 0x0011) as u8;
-                }
-                Instruction::SUBxy(x, y) => {
-                    if self.registers[x as usize] > self.registers[y as usize] {
-                        self.registers[15] = 1;
-                        self.registers[x as usize] =
-                            self.registers[x as usize] - self.registers[y as usize];
-                    } else {
-                        self.registers[15] = 0;
-                        self.registers[x as usize] = 0;
-                    }
+            }
+            Instruction::LDIAddr(addr) => self.i = addr,
+            Instruction::JPV0Addr(addr) => {
+                let x = if self.quirks.jump_vx { addr >> 8 } else { 0 };
+                self.program_counter = addr + self.registers[x as usize] as u16;
+                return Ok(());
+            }
+            Instruction::RNDxByte(x, b) => {
+                let v = self.rng.next_u8(&self.memory.0);
+                self.registers[x as usize] = v & b;
+            }
+            Instruction::DRWxyn(x, y, n) => {
+                if self.quirks.display_wait && self.drawn {
+                    // stall on this op until the next vertical blank
+                    return Ok(());
                 }
-                Instruction::SHRxy(x, _y) => {
-                    self.registers[0] = self.registers[x as usize] & 1;
-                    self.registers[x as usize] >>= 1;
-                }
-                Instruction::SUBNxy(x, y) => {
-                    if self.registers[x as usize] < self.registers[y as usize] {
-                        self.registers[15] = 1;
-                        self.registers[x as usize] =
-                            self.registers[y as usize] - self.registers[x as usize];
-                    } else {
-                        self.registers[15] = 0;
-                        self.registers[x as usize] = 0;
-                    }
+                self.drawn = true;
+                let vx = self.registers[x as usize];
+                let vy = self.registers[y as usize];
+                // one sprite per selected XO-CHIP plane, back to back
+                let planes = self.framebuffer.planes().count_ones() as u16;
+                let collision = if n == 0 {
+                    let nslice = self.memory.read(self.i, 32 * planes);
+                    self.framebuffer.draw_wide_at(vx, vy, &nslice)
+                } else {
+                    let nslice = self.memory.read(self.i, n as u16 * planes);
+                    self.framebuffer.draw_at(vx, vy, &nslice)
+                };
+                self.registers[15] = collision as u8;
+            }
+            Instruction::SKPx(x) => {
+                if self.keys[(self.registers[x as usize] & 0xF) as usize] {
+                    self.skip_next();
                 }
-                Instruction::SHLxy(x, _y) => {
-                    self.registers[0] = (self.registers[x as usize] & 0b10000000) >> 7;
-                    self.registers[x as usize] <<= 1;
+            }
+            Instruction::SKPNPx(x) => {
+                if !self.keys[(self.registers[x as usize] & 0xF) as usize] {
+                    self.skip_next();
                 }
-                Instruction::SNExy(x, y) => {
-                    if self.registers[x as usize] != self.registers[y as usize] {
-                        self.program_counter += 2;
+            }
+            Instruction::LDILong => {
+                self.i = self.memory.word(self.program_counter.wrapping_add(2));
+                self.program_counter = self.program_counter.wrapping_add(2);
+            }
+            Instruction::PLANEn(n) => self.framebuffer.set_planes(n),
+            Instruction::AUDIO => {
+                self.audio_pattern
+                    .copy_from_slice(&self.memory.read(self.i, 16));
+            }
+            Instruction::PITCHx(x) => {
+                self.pitch = self.registers[x as usize];
+            }
+            Instruction::LDxDt(x) => {
+                self.registers[x as usize] = self.delay_timer;
+            }
+            Instruction::LDxK(x) => {
+                // block (by not advancing) until a key is pressed and released
+                match self.key_wait {
+                    Some(k) if !self.keys[k as usize] => {
+                        self.registers[x as usize] = k;
+                        self.key_wait = None;
+                    }
+                    Some(_) => return Ok(()),
+                    None => {
+                        self.key_wait = self.keys.iter().position(|k| *k).map(|k| k as u8);
+                        return Ok(());
                     }
                 }
-                Instruction::LDIAddr(addr) => self.i = addr,
-                Instruction::JPV0Addr(addr) => {
-                    self.program_counter = addr + self.registers[0] as u16;
-                    return Ok(());
+            }
+            Instruction::LDDTx(x) => {
+                self.delay_timer = self.registers[x as usize];
+            }
+            Instruction::LDSTx(x) => {
+                self.sound_timer = self.registers[x as usize];
+            }
+            Instruction::ADDIx(x) => {
+                self.i = self.i.wrapping_add(self.registers[x as usize] as u16);
+            }
+            Instruction::LDFx(x) => {
+                self.i = FONT_ADDR + 5 * (self.registers[x as usize] & 0xF) as u16;
+            }
+            Instruction::LDHFx(x) => {
+                self.i = BIG_FONT_ADDR + 10 * (self.registers[x as usize] & 0xF) as u16;
+            }
+            Instruction::LDBx(x) => {
+                let vx = self.registers[x as usize];
+                self.memory
+                    .write(self.i, &[vx / 100, (vx / 10) % 10, vx % 10]);
+            }
+            Instruction::LDIx(x) => {
+                let n = x as usize + 1;
+                self.memory.write(self.i, &self.registers[..n]);
+                self.increment_i(x);
+            }
+            Instruction::LDxI(x) => {
+                let n = x as u16 + 1;
+                self.registers[..n as usize].copy_from_slice(&self.memory.read(self.i, n));
+                self.increment_i(x);
+            }
+            Instruction::LDRx(x) => {
+                let n = x as usize + 1;
+                self.rpl[..n].copy_from_slice(&self.registers[..n]);
+                if let Some(path) = &self.rpl_path {
+                    std::fs::write(path, self.rpl)?;
                 }
-                Instruction::RNDxByte(x, b) => {
-                    let v: u8 = rand::random();
-                    self.registers[x as usize] = v & b;
-                }
-                Instruction::DRWxyn(x, y, n) => {
-                    let vx = self.registers[x as usize];
-                    let vy = self.registers[y as usize];
-                    let nslice = self.memory.get_slice(self.i, n as u16);
-                    let collision = self
-                        .framebuffer
-                        .as_mut()
-                        .context("window not initialized")?
-                        .draw_at(vx, vy, nslice);
-                    self.registers[15] = collision as u8;
-                }
-                Instruction::SKPx(x) => todo!(),
-                Instruction::SKPNPx(x) => todo!(),
-                Instruction::LDxDt(x) => todo!(),
-                Instruction::LDxK(x) => todo!(),
-                Instruction::LDDTx(x) => todo!(),
-                Instruction::LDSTx(x) => todo!(),
-                Instruction::ADDIx(x) => todo!(),
-                Instruction::LDFx(x) => todo!(),
-                Instruction::LDBx(x) => todo!(),
-                Instruction::LDIx(x) => todo!(),
-                Instruction::LDxI(x) => todo!(),
-            };
-            self.program_counter += 2;
-        }
+            }
+            Instruction::LDxR(x) => {
+                let n = x as usize + 1;
+                self.registers[..n].copy_from_slice(&self.rpl[..n]);
+            }
+        };
+        self.program_counter = self.program_counter.wrapping_add(2);
         Ok(())
     }
 
+    /// skip the next op, which is two words long for an XO-CHIP long load
+    fn skip_next(&mut self) {
+        let next = self.memory.word(self.program_counter.wrapping_add(2));
+        self.program_counter = self.program_counter.wrapping_add(match next {
+            0xF000 => 4,
+            _ => 2,
+        });
+    }
+
+    /// registers VX to VY for XO-CHIP save/load, in reverse if x > y
+    fn register_range(&self, x: u8, y: u8) -> Box<dyn Iterator<Item = usize>> {
+        let (x, y) = (x as usize, y as usize);
+        if x <= y {
+            Box::new(x..=y)
+        } else {
+            Box::new((y..=x).rev())
+        }
+    }
+
+    /// advance I after a Fx55/Fx65 transfer of V0..=VX
+    fn increment_i(&mut self, x: u8) {
+        self.i = self.i.wrapping_add(match self.quirks.memory_increment {
+            MemoryIncrement::None => 0,
+            MemoryIncrement::X => x as u16,
+            MemoryIncrement::XPlusOne => x as u16 + 1,
+        });
+    }
+
     pub fn text(i: char) -> [u8; 5] {
         match i {
             '0' => [0xF0, 0x90, 0x90, 0x90, 0xF0],
@@ -248,4 +713,187 @@ impl Processor {
             _ => panic!("invalid: {i}"),
         }
     }
+
+    /// SUPER-CHIP 8x10 digits, a-f follow the same style
+    pub fn big_text(i: char) -> [u8; 10] {
+        match i {
+            '0' => [0x3C, 0x7E, 0xE7, 0xC3, 0xC3, 0xC3, 0xC3, 0xE7, 0x7E, 0x3C],
+            '1' => [0x18, 0x38, 0x58, 0x18, 0x18, 0x18, 0x18, 0x18, 0x18, 0x3C],
+            '2' => [0x3E, 0x7F, 0xC3, 0x06, 0x0C, 0x18, 0x30, 0x60, 0xFF, 0xFF],
+            '3' => [0x3C, 0x7E, 0xC3, 0x03, 0x0E, 0x0E, 0x03, 0xC3, 0x7E, 0x3C],
+            '4' => [0x06, 0x0E, 0x1E, 0x36, 0x66, 0xC6, 0xFF, 0xFF, 0x06, 0x06],
+            '5' => [0xFF, 0xFF, 0xC0, 0xC0, 0xFC, 0xFE, 0x03, 0xC3, 0x7E, 0x3C],
+            '6' => [0x3E, 0x7C, 0xC0, 0xC0, 0xFC, 0xFE, 0xC3, 0xC3, 0x7E, 0x3C],
+            '7' => [0xFF, 0xFF, 0x03, 0x06, 0x0C, 0x18, 0x30, 0x60, 0x60, 0x60],
+            '8' => [0x3C, 0x7E, 0xC3, 0xC3, 0x7E, 0x7E, 0xC3, 0xC3, 0x7E, 0x3C],
+            '9' => [0x3C, 0x7E, 0xC3, 0xC3, 0x7F, 0x3F, 0x03, 0x03, 0x3E, 0x7C],
+            'a' => [0x18, 0x3C, 0x66, 0xC3, 0xC3, 0xFF, 0xFF, 0xC3, 0xC3, 0xC3],
+            'b' => [0xFC, 0xFE, 0xC3, 0xC3, 0xFE, 0xFE, 0xC3, 0xC3, 0xFE, 0xFC],
+            'c' => [0x3C, 0x7E, 0xC3, 0xC0, 0xC0, 0xC0, 0xC0, 0xC3, 0x7E, 0x3C],
+            'd' => [0xFC, 0xFE, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xFE, 0xFC],
+            'e' => [0xFF, 0xFF, 0xC0, 0xC0, 0xFC, 0xFC, 0xC0, 0xC0, 0xFF, 0xFF],
+            'f' => [0xFF, 0xFF, 0xC0, 0xC0, 0xFC, 0xFC, 0xC0, 0xC0, 0xC0, 0xC0],
+            _ => panic!("invalid: {i}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// processor about to run `ops` from `at`, the zeros before it are
+    /// skipped `SYS` ops
+    fn boot(platform: Platform, at: u16, ops: &[u16]) -> Processor {
+        let bytes: Vec<u8> = ops.iter().flat_map(|op| op.to_be_bytes()).collect();
+        let start = at as usize - 0x200;
+        let mut rom = Rom::with_len(start + bytes.len());
+        rom.write(start, &bytes);
+        let mut proc = Processor::with_platform(rom, platform);
+        proc.run((at as usize - 0x200) / 2).unwrap();
+        assert_eq!(proc.program_counter(), at);
+        proc
+    }
+
+    /// carry on from `pc` with `bytes` stored at `addr`
+    fn poke(proc: &mut Processor, pc: u16, addr: u16, bytes: &[u8]) {
+        let mut s = proc.snapshot();
+        s.program_counter = pc;
+        for (k, b) in bytes.iter().enumerate() {
+            s.memory[addr as usize + k] = *b;
+        }
+        proc.restore(s);
+    }
+
+    #[test]
+    fn bcd_wraps_round_memory() {
+        // LD V0, 123; LD I, 0xFFF; LD B, V0
+        let mut proc = boot(Platform::Vip, 0x200, &[0x607B, 0xAFFF, 0xF033]);
+        proc.run(3).unwrap();
+        let m = proc.memory();
+        assert_eq!([m[0xFFF], m[0], m[1]], [1, 2, 3]);
+    }
+
+    #[test]
+    fn register_transfers_wrap_round_memory() {
+        // LD V0..V3, 1..4; LD I, 0xFFF; LD [I], V3; LD I, 0xFFF; LD V3, [I]
+        let ops = [
+            0x6001, 0x6102, 0x6203, 0x6304, 0xAFFF, 0xF355, 0x6000, 0x6300, 0xAFFF, 0xF365,
+        ];
+        let mut proc = boot(Platform::Vip, 0x200, &ops);
+        proc.run(ops.len()).unwrap();
+        let m = proc.memory();
+        assert_eq!([m[0xFFF], m[0], m[1], m[2]], [1, 2, 3, 4]);
+        assert_eq!(proc.registers()[..4], [1, 2, 3, 4]);
+        assert_eq!(proc.i(), 0xFFF + 4);
+    }
+
+    #[test]
+    fn sprites_wrap_round_memory() {
+        // LD I, 0xFFE; DRW V0, V0, 5
+        let mut proc = boot(Platform::Vip, 0x200, &[0xAFFE, 0xD005]);
+        proc.run(2).unwrap();
+        // rows from 0xFFE, 0xFFF and 0x000..=0x002, all blank
+        assert_eq!(proc.registers()[15], 0);
+        assert_eq!(proc.program_counter(), 0x204);
+    }
+
+    #[test]
+    fn i_wraps_past_the_last_address() {
+        // LD I, long 0xFFFF; LD [I], V0
+        let mut proc = boot(Platform::XoChip, 0x200, &[0xF000, 0xFFFF, 0xF055]);
+        proc.run(2).unwrap();
+        assert_eq!(proc.i(), 0);
+    }
+
+    #[test]
+    fn rewinding_restores_the_clock_phase() {
+        // 90 ips runs 1 and 2 ops on alternate frames
+        let mut proc = boot(Platform::Vip, 0x200, &[0x7001, 0x1200]);
+        let mut state = RendererState {
+            clock: Clock::new(90),
+            ..Default::default()
+        };
+        for _ in 0..3 {
+            state.frame(&mut proc).unwrap();
+        }
+        let before = (proc.snapshot(), state.clock.cycle_remainder());
+        state.frame(&mut proc).unwrap();
+        assert!(state.back(&mut proc).unwrap());
+        assert_eq!((proc.snapshot(), state.clock.cycle_remainder()), before);
+
+        state.frame(&mut proc).unwrap();
+        let mut again = boot(Platform::Vip, 0x200, &[0x7001, 0x1200]);
+        let mut clock = Clock::new(90);
+        clock.run_frames(&mut again, 4).unwrap();
+        assert_eq!(again.registers()[0], proc.registers()[0]);
+    }
+
+    #[test]
+    fn ops_on_the_last_byte_wrap() {
+        // LD I, 0x123 split over 0xFFF and 0x000
+        let mut proc = boot(Platform::Vip, 0x200, &[]);
+        poke(&mut proc, 0xFFF, 0xFFF, &[0xA1]);
+        poke(&mut proc, 0xFFF, 0x000, &[0x23]);
+        proc.step().unwrap();
+        assert_eq!(proc.i(), 0x123);
+        // 4 KiB has nowhere left to go
+        assert!(proc.step().is_err());
+
+        let mut proc = boot(Platform::XoChip, 0x200, &[]);
+        poke(&mut proc, 0xFFFF, 0xFFFF, &[0xA1]);
+        poke(&mut proc, 0xFFFF, 0x0000, &[0x23]);
+        proc.step().unwrap();
+        assert_eq!((proc.i(), proc.program_counter()), (0x123, 0x0001));
+    }
+
+    #[test]
+    fn long_loads_wrap_in_64k() {
+        let mut proc = boot(Platform::XoChip, 0xFFFE, &[0xF000]);
+        poke(&mut proc, 0xFFFE, 0x0000, &[0x12, 0x34]);
+        proc.step().unwrap();
+        assert_eq!((proc.i(), proc.program_counter()), (0x1234, 0x0002));
+
+        // SE V0, 0 skips the whole long load, operand and all
+        let mut proc = boot(Platform::XoChip, 0xFFFC, &[0x3000, 0xF000]);
+        proc.step().unwrap();
+        assert_eq!(proc.program_counter(), 0x0002);
+    }
+
+    #[test]
+    fn program_counter_wraps_in_64k() {
+        // LD V0, 5 in the last word
+        let mut proc = boot(Platform::XoChip, 0xFFFE, &[0x6005]);
+        proc.step().unwrap();
+        assert_eq!(proc.registers()[0], 5);
+        assert_eq!(proc.program_counter(), 0);
+
+        // CALL 0x202 returns to 0x0000
+        let mut proc = boot(Platform::XoChip, 0xFFFE, &[0x2202]);
+        proc.step().unwrap();
+        assert_eq!(proc.stack(), [0]);
+    }
+
+    #[test]
+    fn xo_chip_transfers_wrap_in_64k() {
+        let ops = [
+            0x6001, 0x6102, // LD V0, 1; LD V1, 2
+            0xF000, 0xFFFF, // LD I, long 0xFFFF
+            0x5012, // SAVE V0 - V1
+            0x6000, 0x6100, // LD V0, 0; LD V1, 0
+            0x5013, // LOAD V0 - V1
+            0xF002, // AUDIO
+            0xF301, // PLANE 3
+            0xD001, // DRW V0, V0, 1, one row for each plane
+        ];
+        let mut proc = boot(Platform::XoChip, 0x200, &ops);
+        proc.run(10).unwrap();
+        let m = proc.memory();
+        assert_eq!([m[0xFFFF], m[0]], [1, 2]);
+        assert_eq!(proc.registers()[..2], [1, 2]);
+        assert_eq!(proc.audio_pattern()[..2], [1, 2]);
+        // at (1, 1), plane 1 draws the byte at 0xFFFF and plane 2 the one at 0
+        let fb = proc.framebuffer();
+        assert_eq!([fb.color(7, 1), fb.color(8, 1)], [2, 1]);
+    }
 }
@@ -0,0 +1,121 @@
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+use super::framebuffer::EdgeMode;
+
+/// interpreter the quirks preset is taken from
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Platform {
+    /// the original COSMAC VIP interpreter
+    #[default]
+    Vip,
+    /// CHIP-48 on the HP-48 calculators
+    Chip48,
+    /// SUPER-CHIP 1.1
+    Schip,
+    /// Octo's XO-CHIP, with 64 KiB of memory
+    XoChip,
+}
+
+/// where Fx55/Fx65 leave I after transferring V0..=VX
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MemoryIncrement {
+    /// I is left untouched
+    #[default]
+    None,
+    /// I += X, the CHIP-48 off by one
+    X,
+    /// I += X + 1, pointing past the last register
+    XPlusOne,
+}
+
+/// Quirks
+///
+/// behaviours that differ between chip8 interpreters, roms tend to only
+/// run correctly with the set they were written against.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Quirks {
+    /// 8xy6/8xyE shift VY into VX, otherwise VX is shifted in place
+    pub shift_vy: bool,
+    /// how Fx55/Fx65 advance I
+    pub memory_increment: MemoryIncrement,
+    /// 8xy1/8xy2/8xy3 reset VF to 0
+    pub vf_reset: bool,
+    /// Bnnn acts as Bxnn, jumping to xnn + VX instead of nnn + V0
+    pub jump_vx: bool,
+    /// DRW waits for the vertical blank, allowing one draw per frame
+    pub display_wait: bool,
+    /// sprites wrap around the screen edge instead of clipping
+    pub wrap: bool,
+}
+
+impl Default for Quirks {
+    fn default() -> Self {
+        Self::cosmac_vip()
+    }
+}
+
+impl Quirks {
+    pub fn cosmac_vip() -> Self {
+        Self {
+            shift_vy: true,
+            memory_increment: MemoryIncrement::XPlusOne,
+            vf_reset: true,
+            jump_vx: false,
+            display_wait: true,
+            wrap: false,
+        }
+    }
+
+    pub fn chip48() -> Self {
+        Self {
+            shift_vy: false,
+            memory_increment: MemoryIncrement::X,
+            vf_reset: false,
+            jump_vx: true,
+            display_wait: false,
+            wrap: false,
+        }
+    }
+
+    pub fn superchip() -> Self {
+        Self {
+            shift_vy: false,
+            memory_increment: MemoryIncrement::None,
+            vf_reset: false,
+            jump_vx: true,
+            display_wait: false,
+            wrap: false,
+        }
+    }
+
+    pub fn xo_chip() -> Self {
+        Self {
+            shift_vy: true,
+            memory_increment: MemoryIncrement::XPlusOne,
+            vf_reset: false,
+            jump_vx: false,
+            display_wait: false,
+            wrap: true,
+        }
+    }
+
+    pub fn edge_mode(&self) -> EdgeMode {
+        if self.wrap {
+            EdgeMode::Wrap
+        } else {
+            EdgeMode::Clip
+        }
+    }
+}
+
+impl From<Platform> for Quirks {
+    fn from(value: Platform) -> Self {
+        match value {
+            Platform::Vip => Quirks::cosmac_vip(),
+            Platform::Chip48 => Quirks::chip48(),
+            Platform::Schip => Quirks::superchip(),
+            Platform::XoChip => Quirks::xo_chip(),
+        }
+    }
+}
@@ -0,0 +1,214 @@
+use std::collections::VecDeque;
+
+use color_eyre::Result;
+
+use super::state::Snapshot;
+
+/// memory the rewind buffer uses when none is configured
+pub const DEFAULT_REWIND_BUDGET: usize = 16 * 1024 * 1024;
+/// unchanged bytes between two changed runs that still get merged into one
+const RUN_GAP: usize = 8;
+/// bookkeeping counted against the budget for every run
+const RUN_OVERHEAD: usize = std::mem::size_of::<(usize, Vec<u8>)>();
+
+/// changes turning one encoded snapshot into the frame before it
+#[derive(Debug, Clone)]
+struct Delta {
+    /// length of the older encoding
+    len: usize,
+    /// (offset, older bytes) for every changed run
+    runs: Vec<(usize, Vec<u8>)>,
+}
+
+impl Delta {
+    /// delta taking `new` back to `old`
+    fn between(new: &[u8], old: &[u8]) -> Self {
+        let mut runs: Vec<(usize, Vec<u8>)> = vec![];
+        for (i, b) in old.iter().enumerate() {
+            if new.get(i) == Some(b) {
+                continue;
+            }
+            match runs.last_mut() {
+                Some((start, bytes)) if i - (*start + bytes.len()) <= RUN_GAP => {
+                    bytes.extend_from_slice(&old[*start + bytes.len()..=i]);
+                }
+                _ => runs.push((i, vec![*b])),
+            }
+        }
+        Self {
+            len: old.len(),
+            runs,
+        }
+    }
+
+    fn apply(&self, bytes: &mut Vec<u8>) {
+        bytes.resize(self.len, 0);
+        for (start, run) in &self.runs {
+            bytes[*start..*start + run.len()].copy_from_slice(run);
+        }
+    }
+
+    /// bytes counted against the budget
+    fn size(&self) -> usize {
+        std::mem::size_of::<Self>()
+            + self
+                .runs
+                .iter()
+                .map(|(_, r)| r.len() + RUN_OVERHEAD)
+                .sum::<usize>()
+    }
+}
+
+/// Rewind
+///
+/// ring buffer of per-frame snapshots for running a game backwards, each
+/// with the `Clock` phase it ran on so running forwards again repeats the
+/// same ops. only the newest frame is kept whole, every older one is
+/// stored as the bytes that differ from the frame after it, most of memory
+/// never changes so this is usually a few dozen bytes. the oldest frames
+/// are dropped once the deltas outgrow the budget.
+#[derive(Debug, Clone)]
+pub struct Rewind {
+    budget: usize,
+    /// bytes held by `deltas`
+    used: usize,
+    /// encoding of the newest frame
+    current: Option<Vec<u8>>,
+    /// deltas back from `current`, newest last
+    deltas: VecDeque<Delta>,
+}
+
+impl Default for Rewind {
+    fn default() -> Self {
+        Self::new(DEFAULT_REWIND_BUDGET)
+    }
+}
+
+impl Rewind {
+    /// rewind buffer using at most `budget` bytes on top of the newest frame
+    pub fn new(budget: usize) -> Self {
+        Self {
+            budget,
+            used: 0,
+            current: None,
+            deltas: VecDeque::new(),
+        }
+    }
+
+    pub fn budget(&self) -> usize {
+        self.budget
+    }
+
+    /// bytes held by the older frames
+    pub fn used(&self) -> usize {
+        self.used
+    }
+
+    /// frames that can be stepped back to
+    pub fn len(&self) -> usize {
+        self.deltas.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.deltas.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.used = 0;
+        self.current = None;
+        self.deltas.clear();
+    }
+
+    /// record the frame that just ran, and the clock's `cycle_remainder`
+    pub fn push(&mut self, snapshot: &Snapshot, cycle_remainder: u64) -> Result<()> {
+        if self.budget == 0 {
+            return Ok(());
+        }
+        let new = bincode::serialize(&(snapshot, cycle_remainder))?;
+        if let Some(old) = self.current.replace(new) {
+            let delta = Delta::between(self.current.as_deref().unwrap_or_default(), &old);
+            self.used += delta.size();
+            self.deltas.push_back(delta);
+        }
+        while self.used > self.budget {
+            let Some(oldest) = self.deltas.pop_front() else {
+                break;
+            };
+            self.used -= oldest.size();
+        }
+        Ok(())
+    }
+
+    /// step back a frame to its snapshot and `cycle_remainder`, `None` once
+    /// the oldest frame kept is reached
+    pub fn rewind(&mut self) -> Result<Option<(Snapshot, u64)>> {
+        let (Some(current), Some(delta)) = (&mut self.current, self.deltas.pop_back()) else {
+            return Ok(None);
+        };
+        self.used -= delta.size();
+        delta.apply(current);
+        Ok(Some(bincode::deserialize(current)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::{asm::assemble, clock::Clock, Processor};
+
+    fn frames(n: usize) -> Vec<(Snapshot, u64)> {
+        let rom = assemble(
+            "loop:  LD I, sprite
+                    DRW V0, V1, 1
+                    ADD V0, 1
+                    LD [I], V1
+                    JP loop
+            sprite: db 0x80",
+        )
+        .unwrap();
+        let mut proc = Processor::with_rom(rom);
+        let mut clock = Clock::new(700);
+        (0..n)
+            .map(|_| {
+                clock.frame(&mut proc).unwrap();
+                (proc.snapshot(), clock.cycle_remainder())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn rewinds_frame_by_frame() {
+        let frames = frames(30);
+        let mut rewind = Rewind::default();
+        for (s, phase) in &frames {
+            rewind.push(s, *phase).unwrap();
+        }
+        assert_eq!(rewind.len(), 29);
+        for f in frames.iter().rev().skip(1) {
+            assert_eq!(rewind.rewind().unwrap().as_ref(), Some(f));
+        }
+        assert!(rewind.rewind().unwrap().is_none() && rewind.used() == 0);
+    }
+
+    #[test]
+    fn deltas_stay_small_and_within_budget() {
+        let frames = frames(100);
+        let mut rewind = Rewind::default();
+        rewind.push(&frames[0].0, frames[0].1).unwrap();
+        rewind.push(&frames[1].0, frames[1].1).unwrap();
+        assert!(
+            rewind.used() < 1024,
+            "{} bytes for one frame",
+            rewind.used()
+        );
+
+        let budget = rewind.used() * 10;
+        let mut rewind = Rewind::new(budget);
+        for (s, phase) in &frames {
+            rewind.push(s, *phase).unwrap();
+        }
+        assert!(rewind.used() <= budget && rewind.len() < 20);
+        // the newest frames are the ones kept
+        assert_eq!(rewind.rewind().unwrap().as_ref(), Some(&frames[98]));
+    }
+}
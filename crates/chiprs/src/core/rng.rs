@@ -0,0 +1,149 @@
+use std::fmt;
+
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+/// address of the 256 byte table the rom indexed generator reads
+const ROM_TABLE: usize = 0x200;
+
+/// generator picked on the command line
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum RngKind {
+    /// a splitmix64 stream
+    #[default]
+    Splitmix,
+    /// adds bytes of the rom into a rotating byte, see `Rng::RomIndexed`
+    RomIndexed,
+}
+
+/// Rng
+///
+/// the source `RNDxByte` draws from, part of the machine state so save
+/// states, rewinds and movies repeat the same numbers.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Rng {
+    /// splitmix64, any seed gives a full 2^64 period
+    Splitmix { seed: u64, state: u64 },
+    /// adds the byte of the rom at a counter into the last result and
+    /// rotates it. modelled on the COSMAC VIP, which indexes its own
+    /// interpreter code the same way, but not its sequence: the first 256
+    /// bytes of the program stand in for the interpreter. it keeps the
+    /// VIP's short, memory dependent cycle.
+    RomIndexed { counter: u8, value: u8 },
+    /// `bytes` handed out in order and repeated, for tests
+    Sequence { bytes: Vec<u8>, next: usize },
+}
+
+impl Default for Rng {
+    fn default() -> Self {
+        Self::seeded(0)
+    }
+}
+
+impl Rng {
+    pub fn seeded(seed: u64) -> Self {
+        Self::Splitmix { seed, state: seed }
+    }
+
+    /// generator of `kind` started from `seed`, the rom indexed one only
+    /// keeps 16 bits
+    pub fn new(kind: RngKind, seed: u64) -> Self {
+        match kind {
+            RngKind::Splitmix => Self::seeded(seed),
+            RngKind::RomIndexed => Self::RomIndexed {
+                counter: seed as u8,
+                value: (seed >> 8) as u8,
+            },
+        }
+    }
+
+    pub fn sequence(bytes: impl Into<Vec<u8>>) -> Self {
+        Self::Sequence {
+            bytes: bytes.into(),
+            next: 0,
+        }
+    }
+
+    /// next random byte, `memory` is only read by the rom indexed generator
+    pub fn next_u8(&mut self, memory: &[u8]) -> u8 {
+        match self {
+            Self::Splitmix { state, .. } => (splitmix64(state) >> 56) as u8,
+            Self::RomIndexed { counter, value } => {
+                *counter = counter.wrapping_add(1);
+                let table = memory.get(ROM_TABLE + *counter as usize).copied();
+                *value = value.wrapping_add(table.unwrap_or(0)).rotate_right(1);
+                *value
+            }
+            Self::Sequence { bytes, next } => {
+                let Some(&b) = bytes.get(*next % bytes.len().max(1)) else {
+                    return 0;
+                };
+                *next += 1;
+                b
+            }
+        }
+    }
+}
+
+fn splitmix64(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+impl fmt::Display for Rng {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Splitmix { seed, .. } => write!(f, "splitmix seed {seed}"),
+            Self::RomIndexed { counter, value } => {
+                let seed = (*value as u16) << 8 | *counter as u16;
+                write!(f, "rom indexed seed {seed}")
+            }
+            Self::Sequence { bytes, .. } => write!(f, "sequence of {} bytes", bytes.len()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bytes(rng: &mut Rng, memory: &[u8]) -> Vec<u8> {
+        (0..32).map(|_| rng.next_u8(memory)).collect()
+    }
+
+    #[test]
+    fn same_seed_same_stream() {
+        let a = bytes(&mut Rng::seeded(42), &[]);
+        assert_eq!(a, bytes(&mut Rng::seeded(42), &[]));
+        assert_ne!(a, bytes(&mut Rng::seeded(43), &[]));
+        // reference splitmix64 output for seed 0
+        assert_eq!(splitmix64(&mut 0), 0xE220_A839_7B1D_CDAF);
+    }
+
+    #[test]
+    fn rom_indexed_and_sequence_generators() {
+        let mut memory = vec![0; 0x1000];
+        memory[0x201..0x204].copy_from_slice(&[0x10, 0x20, 0x03]);
+        let mut rom = Rng::new(RngKind::RomIndexed, 0x0100);
+        assert_eq!([0, 1, 2].map(|_| rom.next_u8(&memory)), [0x88, 0x54, 0xAB]);
+        assert_eq!(rom.to_string(), "rom indexed seed 43779");
+
+        let mut seq = Rng::sequence([7, 9]);
+        assert_eq!(bytes(&mut seq, &[])[..5], [7, 9, 7, 9, 7]);
+        assert_eq!(Rng::sequence([]).next_u8(&[]), 0);
+    }
+
+    #[test]
+    fn rnd_masks_the_injected_bytes() {
+        use crate::core::{asm::assemble, Processor};
+
+        let rom = assemble("RND V0, 0x0F\nRND V1, 0xFF").unwrap();
+        let mut proc = Processor::with_rom(rom);
+        proc.set_rng(Rng::sequence([0xAB, 0xCD]));
+        proc.run(2).unwrap();
+        assert_eq!(proc.registers()[..2], [0x0B, 0xCD]);
+    }
+}
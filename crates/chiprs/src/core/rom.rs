@@ -1,19 +1,31 @@
 use std::path::Path;
 
-use color_eyre::{eyre::ContextCompat, Result};
+use color_eyre::Result;
 
-use crate::core::instructions::Row;
-
-use super::instructions::Instruction;
+use super::{
+    disassembler::{disassemble, Format},
+    instructions::Instruction,
+};
 
+#[allow(clippy::large_enum_variant)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Rom {
     Base([u8; 3584], usize),
     ETI600([u8; 2560], usize),
+    /// XO-CHIP rom too large for the 4 KiB address space
+    XOChip(Vec<u8>),
 }
 
+/// largest XO-CHIP rom, the 64 KiB address space less the interpreter area
+pub const XO_CHIP_MAX: usize = 0x10000 - 0x200;
+
 impl Rom {
     pub fn load_from_path(path: impl AsRef<Path>) -> std::io::Result<Self> {
-        std::fs::read(path.as_ref()).and_then(|f| match f.len() {
+        std::fs::read(path.as_ref()).and_then(Rom::from_bytes)
+    }
+
+    pub fn from_bytes(f: Vec<u8>) -> std::io::Result<Self> {
+        match f.len() {
             3584 => {
                 let mut arr = [0; 3584];
                 arr.copy_from_slice(&f[..3584]);
@@ -29,37 +41,68 @@ impl Rom {
                 arr[..x].copy_from_slice(&f[..x]);
                 Ok(Rom::Base(arr, x))
             }
+            x if x <= XO_CHIP_MAX => Ok(Rom::XOChip(f)),
             _ => Err(std::io::Error::new(
                 std::io::ErrorKind::NotFound,
                 "Not-Valid ROM file.",
             )),
-        })
+        }
     }
 
-    fn insert(&mut self, index: usize, inst: Instruction) {
+    /// zeroed rom of `len` bytes, loaded at 0x200
+    pub fn with_len(len: usize) -> Self {
+        if len <= 3584 {
+            Rom::Base([0; 3584], len)
+        } else {
+            Rom::XOChip(vec![0; len])
+        }
+    }
+
+    /// address the rom is loaded at, and execution starts from
+    pub fn load_addr(&self) -> u16 {
         match self {
-            Rom::Base(r, _) => {
-                let i = inst.encode();
-                let (a, b) = (i >> 8, i & 0x00FF);
-                let (a, b) = (a as u8, b as u8);
-                r[index] = a;
-                r[index + 1] = b;
+            Rom::ETI600(..) => 0x600,
+            Rom::Base(..) | Rom::XOChip(_) => 0x200,
+        }
+    }
+
+    /// the rom contents, without the padding of the fixed size variants
+    pub fn bytes(&self) -> &[u8] {
+        match self {
+            Rom::Base(x, l) => &x[..*l],
+            Rom::ETI600(x, l) => &x[..*l],
+            Rom::XOChip(x) => x,
+        }
+    }
+
+    pub(crate) fn insert(&mut self, index: usize, inst: Instruction) {
+        self.write(index, &inst.encode().to_be_bytes());
+    }
+
+    /// copy `bytes` in at `index`, growing the rom length to cover them
+    pub(crate) fn write(&mut self, index: usize, bytes: &[u8]) {
+        let end = index + bytes.len();
+        match self {
+            Rom::Base(r, l) => {
+                r[index..end].copy_from_slice(bytes);
+                *l = (*l).max(end);
+            }
+            Rom::ETI600(r, l) => {
+                r[index..end].copy_from_slice(bytes);
+                *l = (*l).max(end);
             }
-            Rom::ETI600(r, _) => {
-                let i = inst.encode();
-                let (a, b) = (i >> 8, i & 0x00FF);
-                let (a, b) = (a as u8, b as u8);
-                r[index] = a;
-                r[index + 1] = b;
+            Rom::XOChip(r) => {
+                if r.len() < end {
+                    r.resize(end, 0);
+                }
+                r[index..end].copy_from_slice(bytes);
             }
         }
     }
 }
 impl Default for Rom {
     fn default() -> Self {
-        let mut rom = Rom::Base([0; 3584], 3584);
-        // project
-        rom
+        Rom::Base([0; 3584], 3584)
     }
 }
 
@@ -75,27 +118,9 @@ impl From<[u8; 2560]> for Rom {
 }
 
 impl Rom {
-    pub fn rom_disassemble(&self) -> Result<()> {
-        let (s, l) = match self {
-            Rom::Base(x, l) => (x.as_slice(), *l),
-            Rom::ETI600(x, l) => (x.as_slice(), *l),
-        };
-        let mut i = 512usize;
-        let mut x = 0;
-        println!("  Idx |  Hex |     Binary |   Name |  Addr |  Byte |  Reg1 |  Reg2 |   N \n--------------------------------------------------------------------------",);
-        for b in &s[..l] {
-            if i % 2 == 0 {
-                x += *b as u16;
-                println!(
-                    " {i:>4} | 0x{b:02x} | 0b{b:08b} | {}",
-                    Instruction::decode(x)?
-                );
-            } else {
-                x = (*b as u16) << 8;
-                println!(" {i:>4} | 0x{b:02x} | 0b{b:08b} | {}", Row::empty());
-            }
-            i += 1;
-        }
+    /// print the flow-aware disassembly in the given format
+    pub fn rom_disassemble(&self, format: Format, sprites: bool) -> Result<()> {
+        print!("{}", disassemble(self).format(format, sprites)?);
         Ok(())
     }
 }
@@ -0,0 +1,282 @@
+use std::{
+    io::{Read, Write},
+    path::Path,
+};
+
+use color_eyre::{eyre::bail, Result};
+use serde::{Deserialize, Serialize};
+
+use super::{
+    framebuffer::{FrameBuffer, PLANES},
+    processor::{MEMORY_SIZE, XO_CHIP_MEMORY_SIZE},
+    quirks::Quirks,
+    rng::Rng,
+};
+
+/// first bytes of every save state file
+pub const STATE_MAGIC: [u8; 4] = *b"C8ST";
+/// version written by `Snapshot::save`, bump it whenever `Snapshot` changes
+/// and keep reading the older layouts in `Snapshot::load`
+pub const STATE_VERSION: u16 = 3;
+
+/// Snapshot
+///
+/// everything a running `Processor` needs to carry on from the same op,
+/// host side settings like the RPL path and the pressed keys are left out.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub registers: [u8; 16],
+    pub i: u16,
+    pub delay_timer: u8,
+    pub sound_timer: u8,
+    pub program_counter: u16,
+    pub stack_pointer: u8,
+    pub stack: [u16; 16],
+    pub memory: Vec<u8>,
+    pub framebuffer: FrameBuffer,
+    pub key_wait: Option<u8>,
+    pub quirks: Quirks,
+    pub drawn: bool,
+    pub halted: bool,
+    pub rpl: [u8; 16],
+    pub audio_pattern: [u8; 16],
+    pub pitch: u8,
+    pub rng: Rng,
+}
+
+/// version 1 layout, from before `RNDxByte` was seeded
+#[derive(Deserialize)]
+struct SnapshotV1 {
+    registers: [u8; 16],
+    i: u16,
+    delay_timer: u8,
+    sound_timer: u8,
+    program_counter: u16,
+    stack_pointer: u8,
+    stack: [u16; 16],
+    memory: Vec<u8>,
+    framebuffer: FrameBuffer,
+    key_wait: Option<u8>,
+    quirks: Quirks,
+    drawn: bool,
+    halted: bool,
+    rpl: [u8; 16],
+    audio_pattern: [u8; 16],
+    pitch: u8,
+}
+
+impl From<SnapshotV1> for Snapshot {
+    fn from(s: SnapshotV1) -> Self {
+        Snapshot {
+            registers: s.registers,
+            i: s.i,
+            delay_timer: s.delay_timer,
+            sound_timer: s.sound_timer,
+            program_counter: s.program_counter,
+            stack_pointer: s.stack_pointer,
+            stack: s.stack,
+            memory: s.memory,
+            framebuffer: s.framebuffer,
+            key_wait: s.key_wait,
+            quirks: s.quirks,
+            drawn: s.drawn,
+            halted: s.halted,
+            rpl: s.rpl,
+            audio_pattern: s.audio_pattern,
+            pitch: s.pitch,
+            rng: Rng::seeded(rand::random()),
+        }
+    }
+}
+
+/// version 2 layout, seeded with a bare splitmix
+#[derive(Deserialize)]
+struct SnapshotV2 {
+    v1: SnapshotV1,
+    seed: u64,
+    state: u64,
+}
+
+impl From<SnapshotV2> for Snapshot {
+    fn from(s: SnapshotV2) -> Self {
+        Snapshot {
+            rng: Rng::Splitmix {
+                seed: s.seed,
+                state: s.state,
+            },
+            ..s.v1.into()
+        }
+    }
+}
+
+impl Snapshot {
+    /// write the magic, version and snapshot
+    pub fn save(&self, mut w: impl Write) -> Result<()> {
+        w.write_all(&STATE_MAGIC)?;
+        w.write_all(&STATE_VERSION.to_le_bytes())?;
+        bincode::serialize_into(w, self)?;
+        Ok(())
+    }
+
+    /// read a snapshot written by this or an older version
+    pub fn load(mut r: impl Read) -> Result<Self> {
+        let mut header = [0; 6];
+        r.read_exact(&mut header)?;
+        if header[..4] != STATE_MAGIC {
+            bail!("not a chiprs save state");
+        }
+        let snapshot: Snapshot = match u16::from_le_bytes([header[4], header[5]]) {
+            1 => bincode::deserialize_from::<_, SnapshotV1>(r)?.into(),
+            2 => bincode::deserialize_from::<_, SnapshotV2>(r)?.into(),
+            3 => bincode::deserialize_from(r)?,
+            v if v > STATE_VERSION => {
+                bail!("save state version {v} is newer than the supported {STATE_VERSION}")
+            }
+            v => bail!("save state version {v} is no longer supported"),
+        };
+        snapshot.validate()?;
+        Ok(snapshot)
+    }
+
+    pub fn save_to_path(&self, path: impl AsRef<Path>) -> Result<()> {
+        let mut buf = vec![];
+        self.save(&mut buf)?;
+        std::fs::write(path, buf)?;
+        Ok(())
+    }
+
+    pub fn load_from_path(path: impl AsRef<Path>) -> Result<Self> {
+        Self::load(std::fs::read(path)?.as_slice())
+    }
+
+    /// reject states a `Processor` would panic on
+    fn validate(&self) -> Result<()> {
+        if ![MEMORY_SIZE, XO_CHIP_MEMORY_SIZE].contains(&self.memory.len()) {
+            bail!("save state has {} bytes of memory", self.memory.len());
+        }
+        if self.stack_pointer as usize > self.stack.len() {
+            bail!(
+                "save state stack pointer {} is out of range",
+                self.stack_pointer
+            );
+        }
+        if !self.framebuffer.is_valid() {
+            bail!("save state display does not match its size");
+        }
+        let planes = self.framebuffer.planes();
+        if planes >> PLANES != 0 {
+            bail!("save state selects planes {planes:#b}, there are only {PLANES}");
+        }
+        if let Some(k @ 16..) = self.key_wait {
+            bail!("save state waits on key {k}, there are only 16");
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::{asm::assemble, Processor};
+
+    fn running() -> Processor {
+        let rom = assemble("LD V0, 5\nCALL sub\nloop: JP loop\nsub: LD DT, V0\nRET").unwrap();
+        let mut proc = Processor::with_rom(rom);
+        proc.run(4).unwrap();
+        proc
+    }
+
+    #[test]
+    fn round_trips_through_bytes() {
+        let proc = running();
+        let mut buf = vec![];
+        proc.snapshot().save(&mut buf).unwrap();
+        assert_eq!(buf[..6], [b'C', b'8', b'S', b'T', 3, 0]);
+
+        let mut restored = Processor::default();
+        restored.restore(Snapshot::load(buf.as_slice()).unwrap());
+        assert_eq!(restored.snapshot(), proc.snapshot());
+        assert_eq!(
+            (restored.program_counter(), restored.delay_timer()),
+            (0x204, 5)
+        );
+    }
+
+    #[test]
+    fn reads_older_states() {
+        let mut proc = running();
+        proc.set_rng(Rng::seeded(5));
+        let mut buf = vec![];
+        proc.snapshot().save(&mut buf).unwrap();
+        // version 2 is the same layout with the rng fields but no variant tag
+        let tag = buf.len() - 20;
+        let mut v2 = buf.clone();
+        v2[4] = 2;
+        v2.drain(tag..tag + 4);
+        assert_eq!(Snapshot::load(v2.as_slice()).unwrap(), proc.snapshot());
+        // and version 1 has no rng at all
+        buf[4] = 1;
+        buf.truncate(tag);
+        let loaded = Snapshot::load(buf.as_slice()).unwrap();
+        assert_eq!(loaded.memory, proc.snapshot().memory);
+    }
+
+    #[test]
+    fn rejects_foreign_and_newer_files() {
+        assert!(Snapshot::load(&b"PNG\0\0\0"[..]).is_err());
+        let mut buf = vec![];
+        running().snapshot().save(&mut buf).unwrap();
+        buf[4] = 4;
+        let err = Snapshot::load(buf.as_slice()).unwrap_err();
+        assert!(err.to_string().contains("newer"), "{err}");
+    }
+
+    #[test]
+    fn rejects_corrupt_states() {
+        let load = |s: &Snapshot| {
+            let mut buf = vec![];
+            s.save(&mut buf).unwrap();
+            Snapshot::load(buf.as_slice())
+        };
+        let good = running().snapshot();
+        assert!(load(&good).is_ok());
+        assert!(load(&Snapshot {
+            key_wait: Some(15),
+            ..good.clone()
+        })
+        .is_ok());
+
+        let err = load(&Snapshot {
+            key_wait: Some(16),
+            ..good.clone()
+        })
+        .unwrap_err();
+        assert!(err.to_string().contains("key 16"), "{err}");
+        assert!(load(&Snapshot {
+            stack_pointer: 17,
+            ..good.clone()
+        })
+        .is_err());
+        assert!(load(&Snapshot {
+            memory: vec![0; 100],
+            ..good.clone()
+        })
+        .is_err());
+
+        let mut fb = serde_json::to_value(&good.framebuffer).unwrap();
+        fb["planes"] = 3.into();
+        let both = serde_json::from_value(fb.clone()).unwrap();
+        assert!(load(&Snapshot {
+            framebuffer: both,
+            ..good.clone()
+        })
+        .is_ok());
+        fb["planes"] = 4.into();
+        let err = load(&Snapshot {
+            framebuffer: serde_json::from_value(fb).unwrap(),
+            ..good
+        })
+        .unwrap_err();
+        assert!(err.to_string().contains("planes"), "{err}");
+    }
+}
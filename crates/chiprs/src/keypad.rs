@@ -0,0 +1,274 @@
+use std::{collections::HashMap, path::Path, str::FromStr};
+
+use color_eyre::{
+    eyre::{bail, eyre},
+    Result,
+};
+use serde::Deserialize;
+use winit::keyboard::KeyCode;
+
+/// host keys a keypad key can be bound to, named as in winit
+const BINDABLE: &[KeyCode] = &[
+    KeyCode::Digit0,
+    KeyCode::Digit1,
+    KeyCode::Digit2,
+    KeyCode::Digit3,
+    KeyCode::Digit4,
+    KeyCode::Digit5,
+    KeyCode::Digit6,
+    KeyCode::Digit7,
+    KeyCode::Digit8,
+    KeyCode::Digit9,
+    KeyCode::KeyA,
+    KeyCode::KeyB,
+    KeyCode::KeyC,
+    KeyCode::KeyD,
+    KeyCode::KeyE,
+    KeyCode::KeyF,
+    KeyCode::KeyG,
+    KeyCode::KeyH,
+    KeyCode::KeyI,
+    KeyCode::KeyJ,
+    KeyCode::KeyK,
+    KeyCode::KeyL,
+    KeyCode::KeyM,
+    KeyCode::KeyN,
+    KeyCode::KeyO,
+    KeyCode::KeyP,
+    KeyCode::KeyQ,
+    KeyCode::KeyR,
+    KeyCode::KeyS,
+    KeyCode::KeyT,
+    KeyCode::KeyU,
+    KeyCode::KeyV,
+    KeyCode::KeyW,
+    KeyCode::KeyX,
+    KeyCode::KeyY,
+    KeyCode::KeyZ,
+    KeyCode::Numpad0,
+    KeyCode::Numpad1,
+    KeyCode::Numpad2,
+    KeyCode::Numpad3,
+    KeyCode::Numpad4,
+    KeyCode::Numpad5,
+    KeyCode::Numpad6,
+    KeyCode::Numpad7,
+    KeyCode::Numpad8,
+    KeyCode::Numpad9,
+    KeyCode::NumpadAdd,
+    KeyCode::NumpadSubtract,
+    KeyCode::NumpadMultiply,
+    KeyCode::NumpadDivide,
+    KeyCode::NumpadDecimal,
+    KeyCode::NumpadEnter,
+    KeyCode::ArrowUp,
+    KeyCode::ArrowDown,
+    KeyCode::ArrowLeft,
+    KeyCode::ArrowRight,
+    KeyCode::Space,
+    KeyCode::Enter,
+    KeyCode::Tab,
+    KeyCode::ShiftLeft,
+    KeyCode::ShiftRight,
+    KeyCode::ControlLeft,
+    KeyCode::ControlRight,
+    KeyCode::AltLeft,
+    KeyCode::AltRight,
+    KeyCode::Comma,
+    KeyCode::Period,
+    KeyCode::Slash,
+    KeyCode::Semicolon,
+    KeyCode::Quote,
+    KeyCode::BracketLeft,
+    KeyCode::BracketRight,
+    KeyCode::Minus,
+    KeyCode::Equal,
+];
+
+/// host key from its winit name, `KeyW`, or a single letter or digit, `w`
+pub fn key_code(name: &str) -> Option<KeyCode> {
+    let name = match name.chars().collect::<Vec<_>>()[..] {
+        [c] if c.is_ascii_digit() => format!("Digit{c}"),
+        [c] if c.is_ascii_alphabetic() => format!("Key{}", c.to_ascii_uppercase()),
+        _ => name.to_string(),
+    };
+    BINDABLE
+        .iter()
+        .find(|k| format!("{k:?}").eq_ignore_ascii_case(&name))
+        .copied()
+}
+
+/// a keypad key bound on the command line, `5=KeyW`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Binding {
+    pub key: u8,
+    pub code: KeyCode,
+}
+
+impl FromStr for Binding {
+    type Err = color_eyre::Report;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let (key, name) = s
+            .split_once('=')
+            .ok_or_else(|| eyre!("expected <hex key>=<host key>, not `{s}`"))?;
+        Ok(Binding {
+            key: hex_key(key)?,
+            code: key_code(name).ok_or_else(|| eyre!("unknown host key `{name}`"))?,
+        })
+    }
+}
+
+fn hex_key(s: &str) -> Result<u8> {
+    match u8::from_str_radix(s.trim(), 16) {
+        Ok(k) if k < 16 => Ok(k),
+        _ => bail!("`{s}` is not a keypad key, expected 0-F"),
+    }
+}
+
+/// keypad section of a keymap file, host keys for each hex key
+#[derive(Debug, Deserialize)]
+struct KeymapFile {
+    keypad: HashMap<String, OneOrMany>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum OneOrMany {
+    One(String),
+    Many(Vec<String>),
+}
+
+/// Keymap
+///
+/// which host key presses the 16 chip8 keys, laid out as
+///
+/// ```text
+/// 1 2 3 C      1 2 3 4
+/// 4 5 6 D  <-  Q W E R
+/// 7 8 9 E      A S D F
+/// A 0 B F      Z X C V
+/// ```
+///
+/// by default. keys are matched by position, so the layout holds on
+/// non-qwerty keyboards.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Keymap {
+    bindings: HashMap<KeyCode, u8>,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        use KeyCode::*;
+        let layout = [
+            (Digit1, 0x1),
+            (Digit2, 0x2),
+            (Digit3, 0x3),
+            (Digit4, 0xC),
+            (KeyQ, 0x4),
+            (KeyW, 0x5),
+            (KeyE, 0x6),
+            (KeyR, 0xD),
+            (KeyA, 0x7),
+            (KeyS, 0x8),
+            (KeyD, 0x9),
+            (KeyF, 0xE),
+            (KeyZ, 0xA),
+            (KeyX, 0x0),
+            (KeyC, 0xB),
+            (KeyV, 0xF),
+        ];
+        Self {
+            bindings: layout.into_iter().collect(),
+        }
+    }
+}
+
+impl Keymap {
+    /// keymap file as toml, replacing the default binding of every key it
+    /// lists, keys left out keep the default
+    ///
+    /// ```toml
+    /// [keypad]
+    /// 5 = "ArrowUp"
+    /// 8 = ["ArrowDown", "s"]
+    /// ```
+    pub fn from_toml(src: &str) -> Result<Self> {
+        let file: KeymapFile = toml::from_str(src)?;
+        let mut keymap = Self::default();
+        for (key, names) in file.keypad {
+            let key = hex_key(&key)?;
+            let names = match names {
+                OneOrMany::One(name) => vec![name],
+                OneOrMany::Many(names) => names,
+            };
+            keymap.bindings.retain(|_, k| *k != key);
+            for name in names {
+                let code = key_code(&name).ok_or_else(|| eyre!("unknown host key `{name}`"))?;
+                keymap.bind(Binding { key, code });
+            }
+        }
+        Ok(keymap)
+    }
+
+    pub fn load_from_path(path: impl AsRef<Path>) -> Result<Self> {
+        Self::from_toml(&std::fs::read_to_string(path)?)
+    }
+
+    /// press `key` with `code`, on top of any other host keys bound to it
+    pub fn bind(&mut self, binding: Binding) {
+        self.bindings.insert(binding.code, binding.key);
+    }
+
+    /// keypad key `code` presses
+    pub fn key(&self, code: KeyCode) -> Option<u8> {
+        self.bindings.get(&code).copied()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_layout() {
+        let keymap = Keymap::default();
+        assert_eq!(keymap.key(KeyCode::Digit4), Some(0xC));
+        assert_eq!(keymap.key(KeyCode::KeyX), Some(0x0));
+        assert_eq!(keymap.key(KeyCode::KeyV), Some(0xF));
+        assert_eq!(keymap.key(KeyCode::Space), None);
+    }
+
+    #[test]
+    fn file_replaces_listed_keys() {
+        let keymap = Keymap::from_toml(
+            r#"
+            [keypad]
+            5 = "ArrowUp"
+            8 = ["ArrowDown", "s"]
+            "#,
+        )
+        .unwrap();
+        assert_eq!(keymap.key(KeyCode::ArrowUp), Some(5));
+        assert_eq!(keymap.key(KeyCode::KeyW), None);
+        assert_eq!(keymap.key(KeyCode::ArrowDown), Some(8));
+        assert_eq!(keymap.key(KeyCode::KeyS), Some(8));
+        assert_eq!(keymap.key(KeyCode::KeyQ), Some(4));
+        assert!(Keymap::from_toml("[keypad]\n10 = \"x\"").is_err());
+        assert!(Keymap::from_toml("[keypad]\n1 = \"Nope\"").is_err());
+    }
+
+    #[test]
+    fn parses_cli_bindings() {
+        assert_eq!(
+            "a=space".parse::<Binding>().unwrap(),
+            Binding {
+                key: 0xA,
+                code: KeyCode::Space
+            }
+        );
+        assert_eq!("F=7".parse::<Binding>().unwrap().code, KeyCode::Digit7);
+        assert!("G=KeyA".parse::<Binding>().is_err());
+        assert!("KeyA".parse::<Binding>().is_err());
+    }
+}
@@ -1,52 +1,356 @@
-mod core;
-use core::{processor::RendererState, Processor, Rom};
-use std::{collections::HashMap, path::PathBuf, time::Instant};
+pub mod core;
+pub mod keypad;
+#[cfg(any(feature = "pixels", feature = "graphicscore"))]
+mod screen;
 
-use clap::Parser;
-use color_eyre::Result;
+use audio_core::{AudioSink, Format as AudioFormat, WavSink};
+use core::{
+    beeper::{Beeper, Tone, Waveform},
+    clock::{Clock, DEFAULT_IPS},
+    debugger::{DebugCommand, Debugger},
+    disassembler::Format,
+    movie::{Movie, Playback},
+    processor::RendererState,
+    quirks::Platform,
+    rewind::Rewind,
+    rng::{Rng, RngKind},
+    state::Snapshot,
+    Processor, Rom,
+};
+use keypad::{Binding, Keymap};
+use std::{
+    io::IsTerminal,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Instant,
+};
+
+use clap::{Parser, Subcommand};
+use color_eyre::{
+    eyre::{ensure, ContextCompat},
+    Result,
+};
 
 use tracing::{info, instrument, warn};
 use winit::{
-    application::ApplicationHandler, event::KeyEvent, keyboard::KeyCode, window::WindowAttributes,
+    application::ApplicationHandler,
+    event::{ElementState, KeyEvent},
+    keyboard::{KeyCode, PhysicalKey},
+    window::WindowAttributes,
 };
 
+/// sample rate of `--wav` files
+const WAV_RATE: u32 = 44_100;
+
 #[derive(Parser, Debug)]
+#[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
 pub struct App {
-    pub rom: PathBuf,
+    #[arg(required = true)]
+    pub rom: Option<PathBuf>,
+    #[command(subcommand)]
+    pub command: Option<Command>,
     #[arg(short, long, alias = "di", default_value_t = false)]
     pub disassemble: bool,
+    /// draw data bytes in the disassembly as sprite rows
+    #[arg(long, requires = "disassemble")]
+    pub sprites: bool,
+    /// disassembly output, `asm` can be rebuilt into the rom with the `asm` subcommand
+    #[arg(long, value_enum, default_value_t = Format::Table, requires = "disassemble")]
+    pub format: Format,
+    /// run the rom headless under an interactive debugger
+    #[arg(long, conflicts_with = "disassemble")]
+    pub debug: bool,
+    /// instructions executed per second, timers always run at 60hz
+    #[arg(long, default_value_t = DEFAULT_IPS)]
+    pub ips: u32,
+    /// interpreter whose quirks the rom expects
+    #[arg(long, value_enum, default_value_t = Platform::Vip)]
+    pub quirks: Platform,
+    /// seed for `RND`, random unless given, so runs can be repeated
+    #[arg(long)]
+    pub seed: Option<u64>,
+    /// generator behind `RND`
+    #[arg(long, value_enum, default_value_t = RngKind::Splitmix)]
+    pub rng: RngKind,
+    /// file SUPER-CHIP RPL flags are saved to, defaults to the rom with a `.rpl` extension
+    #[arg(long)]
+    pub rpl: Option<PathBuf>,
+    /// save state to resume from, F5 saves to it and F9 loads it again.
+    /// defaults to the rom with a `.state` extension
+    #[arg(long)]
+    pub load_state: Option<PathBuf>,
+    /// record the keypad of every frame and the rng to a movie file
+    #[arg(long, value_name = "MOVIE", conflicts_with = "load_state")]
+    pub record: Option<PathBuf>,
+    /// replay a movie written by `--record`, live input takes over once it ends
+    #[arg(long, value_name = "MOVIE", conflicts_with_all = ["load_state", "record"])]
+    pub play: Option<PathBuf>,
+    /// MiB of per-frame snapshots kept for rewinding with backspace, 0 turns it off
+    #[arg(long, default_value_t = 16)]
+    pub rewind_mib: usize,
+    /// toml file remapping the keypad, a `[keypad]` table of hex key = host key names
+    #[arg(long)]
+    pub keymap: Option<PathBuf>,
+    /// bind a keypad key to a host key, `--bind 5=ArrowUp`, can be repeated
+    #[arg(long, value_name = "KEY=HOST_KEY")]
+    pub bind: Vec<Binding>,
+    /// read the keypad from a gamepad, the first one found unless a
+    /// `/dev/input/event*` path is given
+    #[arg(long, num_args = 0..=1, value_name = "DEVICE")]
+    pub gamepad: Option<Option<PathBuf>>,
+    /// replay gamepad input from a script of `<frame> <button> <down|up>` lines
+    #[arg(long)]
+    pub input_script: Option<PathBuf>,
+    /// pitch of the buzzer in hz
+    #[arg(long, default_value_t = Tone::default().pitch)]
+    pub beep_hz: f32,
+    /// loudness of the buzzer, from 0 to 1
+    #[arg(long, default_value_t = Tone::default().volume)]
+    pub volume: f32,
+    /// shape of the buzzer's tone
+    #[arg(long, value_enum, default_value_t = Waveform::Square)]
+    pub waveform: Waveform,
+    /// write the sound to a wav file instead of the sound card
+    #[arg(long)]
+    pub wav: Option<PathBuf>,
+    /// no sound at all
+    #[arg(long, conflicts_with = "wav")]
+    pub mute: bool,
     #[clap(skip)]
     processor: Option<Processor>,
+    /// where the save state hotkeys save to and load from
+    #[clap(skip)]
+    save_path: Option<PathBuf>,
     #[clap(skip)]
-    window: Option<winit::window::Window>,
+    window: Option<Arc<winit::window::Window>>,
+    #[cfg(any(feature = "pixels", feature = "graphicscore"))]
     #[clap(skip)]
-    events: EventMap,
+    screen: Option<screen::Screen>,
+    #[clap(skip)]
+    keys: Keymap,
     #[clap(skip)]
     state: RendererState,
 }
 
-type EventMap = HashMap<KeyCode, KeyEvent>;
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// assemble a source file into a rom
+    Asm {
+        source: PathBuf,
+        /// rom to write, defaults to the source with a `.ch8` extension
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+}
+
+impl Command {
+    #[instrument]
+    pub fn run(self) -> Result<()> {
+        match self {
+            Command::Asm { source, output } => {
+                let output = output.unwrap_or(source.with_extension("ch8"));
+                info!("assembling {} into {}", source.display(), output.display());
+                let rom = core::asm::assemble_file(&source)?;
+                std::fs::write(output, rom.bytes())?;
+            }
+        }
+        Ok(())
+    }
+}
 
 impl App {
+    /// rom path, only ever missing when a subcommand was given
+    pub fn rom(&self) -> Result<&Path> {
+        self.rom.as_deref().context("no rom given")
+    }
+
     #[instrument]
     pub fn disassemble_rom(self) -> Result<()> {
-        info!("disassembling the rom, {}", self.rom.display());
-        Rom::load_from_path(&self.rom)?.rom_disassemble()
+        info!("disassembling the rom, {}", self.rom()?.display());
+        Rom::load_from_path(self.rom()?)?.rom_disassemble(self.format, self.sprites)
+    }
+
+    /// `RND` source picked by `--rng` and `--seed`
+    fn random_source(&self) -> Rng {
+        Rng::new(self.rng, self.seed.unwrap_or_else(rand::random))
+    }
+
+    /// buzzer writing to the `--wav` file or the sound card, none when muted
+    /// or built without the `sound` feature
+    fn beeper(&self) -> Result<Option<Beeper>> {
+        ensure!(
+            (0.0..=1.0).contains(&self.volume),
+            "volume {} is not between 0 and 1",
+            self.volume
+        );
+        ensure!(
+            self.beep_hz.is_finite() && self.beep_hz > 0.0,
+            "beep pitch {} hz is not a positive frequency",
+            self.beep_hz
+        );
+        let tone = Tone {
+            pitch: self.beep_hz,
+            volume: self.volume,
+            waveform: self.waveform,
+        };
+        let sink: Box<dyn AudioSink> = match &self.wav {
+            Some(path) => {
+                info!("writing sound to {}", path.display());
+                Box::new(WavSink::create(path, AudioFormat::mono(WAV_RATE))?)
+            }
+            None if self.mute => return Ok(None),
+            #[cfg(feature = "sound")]
+            None => match audio_core::device::DeviceSink::open(0.1) {
+                Ok(sink) => Box::new(sink),
+                Err(e) => {
+                    warn!("no sound, failed to open the audio device: {e}");
+                    return Ok(None);
+                }
+            },
+            #[cfg(not(feature = "sound"))]
+            None => return Ok(None),
+        };
+        // anything higher aliases back down to a different pitch
+        let nyquist = sink.format().rate as f32 / 2.0;
+        ensure!(
+            self.beep_hz < nyquist,
+            "beep pitch {} hz is not below the {nyquist} hz the output can carry",
+            self.beep_hz
+        );
+        Ok(Some(Beeper::new(sink, tone)))
+    }
+
+    /// run the rom from a debugger prompt, with no window
+    #[instrument]
+    pub fn debug_rom(self) -> Result<()> {
+        let rom = self.rom()?;
+        info!("debugging the rom, {}", rom.display());
+        let mut proc = Processor::with_platform(Rom::load_from_path(rom)?, self.quirks);
+        proc.set_rpl_path(self.rpl.clone().unwrap_or(rom.with_extension("rpl")))?;
+        proc.set_rng(self.random_source());
+        let mut debugger = Debugger::new(proc, self.ips);
+        println!(
+            "{}",
+            debugger.disassemble(debugger.processor().program_counter(), 1)
+        );
+        // commands piped in are read as plain lines, for scripted sessions
+        let mut piped = (!std::io::stdin().is_terminal()).then(|| std::io::stdin().lines());
+        loop {
+            let line = match &mut piped {
+                Some(lines) => match lines.next() {
+                    Some(line) => line?,
+                    None => break,
+                },
+                None => match inquire::Text::new("(chip8)").prompt() {
+                    Ok(line) => line,
+                    Err(
+                        inquire::InquireError::OperationCanceled
+                        | inquire::InquireError::OperationInterrupted,
+                    ) => break,
+                    Err(e) => return Err(e.into()),
+                },
+            };
+            let result = DebugCommand::parse(&line).and_then(|command| {
+                if command == DebugCommand::Quit {
+                    return Ok(None);
+                }
+                debugger.execute(command).map(Some)
+            });
+            match result {
+                Ok(Some(out)) => println!("{}", out.trim_end()),
+                Ok(None) => break,
+                Err(e) => println!("error: {e}"),
+            }
+        }
+        Ok(())
     }
 
     #[instrument]
     pub fn init(&mut self) -> Result<()> {
-        info!(
-            "initializing chiprs processor with rom, {}",
-            self.rom.display()
+        let rom = self.rom()?.to_path_buf();
+        info!("initializing chiprs processor with rom, {}", rom.display());
+        let mut proc = Processor::with_platform(Rom::load_from_path(&rom)?, self.quirks);
+        proc.set_rpl_path(self.rpl.clone().unwrap_or(rom.with_extension("rpl")))?;
+        proc.set_rng(self.random_source());
+        if let Some(path) = &self.load_state {
+            info!("resuming from save state, {}", path.display());
+            proc.restore(Snapshot::load_from_path(path)?);
+        }
+        let mut ips = self.ips;
+        if let Some(path) = &self.play {
+            info!("playing movie, {}", path.display());
+            let playback = Playback::start(Movie::load_from_path(path)?, &mut proc);
+            ips = playback.ips();
+            self.state.playback = Some(playback);
+        }
+        if let Some(path) = &self.record {
+            info!("recording movie with {}, {}", proc.rng(), path.display());
+            self.state.recording = Some(Movie::new(&proc, ips));
+        }
+        _ = self.processor.insert(proc);
+        self.save_path = Some(
+            self.load_state
+                .clone()
+                .unwrap_or(rom.with_extension("state")),
         );
-        _ = self
-            .processor
-            .insert(Processor::with_rom(Rom::load_from_path(&self.rom)?));
+        self.keys = match &self.keymap {
+            Some(path) => Keymap::load_from_path(path)?,
+            None => Keymap::default(),
+        };
+        for binding in &self.bind {
+            self.keys.bind(*binding);
+        }
+        if let Some(device) = &self.gamepad {
+            self.state.inputs.push(gamepad(device.as_deref())?);
+        }
+        if let Some(path) = &self.input_script {
+            let script = input_core::ScriptedInput::load_from_path(path)?;
+            self.state.inputs.push(Box::new(script));
+        }
+        self.state.bindings = input_core::profile::chip8();
+        self.state.clock = Clock::new(ips);
+        self.state.beeper = self.beeper()?;
+        self.state.rewind = Rewind::new(self.rewind_mib * 1024 * 1024);
         // ensure the instant is updated before hand
         _ = self.state.instant.insert(Instant::now());
         Ok(())
     }
+
+    /// write the movie being recorded to the `--record` path
+    pub fn save_movie(&self) -> Result<()> {
+        if let (Some(movie), Some(path)) = (&self.state.recording, &self.record) {
+            info!(
+                "saving {} frame movie, {}",
+                movie.frames.len(),
+                path.display()
+            );
+            movie.save_to_path(path)?;
+        }
+        Ok(())
+    }
+
+    /// write out the end of the sound, completing a `--wav` file
+    pub fn flush_audio(&mut self) -> Result<()> {
+        match &mut self.state.beeper {
+            Some(beeper) => beeper.flush(),
+            None => Ok(()),
+        }
+    }
+}
+
+/// evdev gamepad at `device`, or the first one found
+#[cfg(target_os = "linux")]
+fn gamepad(device: Option<&Path>) -> Result<Box<dyn input_core::InputSource>> {
+    use input_core::gamepad::EvdevGamepad;
+    Ok(Box::new(match device {
+        Some(path) => EvdevGamepad::open(path)?,
+        None => EvdevGamepad::first()?,
+    }))
+}
+
+#[cfg(not(target_os = "linux"))]
+fn gamepad(_device: Option<&Path>) -> Result<Box<dyn input_core::InputSource>> {
+    color_eyre::eyre::bail!("gamepads are only supported on linux")
 }
 
 impl ApplicationHandler for App {
@@ -60,7 +364,7 @@ impl ApplicationHandler for App {
                     .with_resizable(true),
             ) {
                 info!("window created: {:?}", w.id());
-                _ = self.window.insert(w);
+                _ = self.window.insert(Arc::new(w));
             }
         } else {
             warn!("window already exists");
@@ -70,15 +374,16 @@ impl ApplicationHandler for App {
     fn window_event(
         &mut self,
         event_loop: &winit::event_loop::ActiveEventLoop,
-        window_id: winit::window::WindowId,
+        _window_id: winit::window::WindowId,
         event: winit::event::WindowEvent,
     ) {
         let Self {
             rom: _,
             processor,
             window,
-            events: _,
+            keys,
             state,
+            save_path,
             ..
         } = self;
 
@@ -90,19 +395,92 @@ impl ApplicationHandler for App {
                 info!("resized window: {size:?}");
                 window.request_redraw();
                 // resize with pixel buffer
-                if let Some(Processor {
-                    framebuffer: Some(fb),
-                    ..
-                }) = processor
-                {
-                    fb.resize(size.width, size.height);
+                #[cfg(any(feature = "pixels", feature = "graphicscore"))]
+                if let Some(screen) = &mut self.screen {
+                    screen.resize(size.width, size.height);
+                }
+            }
+            winit::event::WindowEvent::CloseRequested => event_loop.exit(),
+            winit::event::WindowEvent::KeyboardInput {
+                event:
+                    KeyEvent {
+                        physical_key: PhysicalKey::Code(KeyCode::Backspace),
+                        state: key_state,
+                        ..
+                    },
+                ..
+            } => state.rewinding = key_state.is_pressed(),
+            winit::event::WindowEvent::KeyboardInput {
+                event:
+                    KeyEvent {
+                        physical_key: PhysicalKey::Code(code @ (KeyCode::F5 | KeyCode::F9)),
+                        state: ElementState::Pressed,
+                        repeat: false,
+                        ..
+                    },
+                ..
+            } => {
+                let (Some(proc), Some(path)) = (processor, save_path) else {
+                    return;
+                };
+                if code == KeyCode::F9 && (state.recording.is_some() || state.playback.is_some()) {
+                    warn!("save states can't be loaded into a movie");
+                    return;
+                }
+                let (action, result) = if code == KeyCode::F5 {
+                    ("save", proc.snapshot().save_to_path(&path))
+                } else {
+                    let loaded = Snapshot::load_from_path(&path).map(|s| proc.restore(s));
+                    ("load", loaded)
+                };
+                match result {
+                    Ok(()) => info!("{action} state {}", path.display()),
+                    Err(e) => warn!("failed to {action} state {}: {e}", path.display()),
+                }
+            }
+            winit::event::WindowEvent::KeyboardInput {
+                event:
+                    KeyEvent {
+                        physical_key: PhysicalKey::Code(code),
+                        state: key_state,
+                        ..
+                    },
+                ..
+            } => {
+                if let (Some(proc), Some(key)) = (processor, keys.key(code)) {
+                    proc.set_key(key, key_state.is_pressed());
+                }
+            }
+            // release events are lost while unfocused, so let go of everything
+            winit::event::WindowEvent::Focused(false) => {
+                if let Some(proc) = processor {
+                    (0..16).for_each(|k| proc.set_key(k, false));
                 }
             }
-            winit::event::WindowEvent::CloseRequested => {}
             winit::event::WindowEvent::RedrawRequested => {
                 // render function with state
                 if let Some(proc) = processor {
-                    _ = proc.run(&window, state);
+                    let now = Instant::now();
+                    let dt = state.instant.replace(now).map(|i| now - i);
+                    if let Err(e) = state.run(proc, dt.unwrap_or_default()) {
+                        warn!("processor step failed: {e}");
+                    }
+                    if proc.halted() {
+                        info!("rom exited");
+                        event_loop.exit();
+                    }
+                    #[cfg(any(feature = "pixels", feature = "graphicscore"))]
+                    {
+                        if self.screen.is_none() {
+                            match screen::Screen::new(window, proc.framebuffer()) {
+                                Ok(s) => _ = self.screen.insert(s),
+                                Err(e) => warn!("failed to create screen: {e}"),
+                            }
+                        }
+                        if let Some(screen) = &mut self.screen {
+                            _ = screen.render(proc.framebuffer());
+                        }
+                    }
                 } else {
                     warn!("processor not initialized.");
                 }
@@ -0,0 +1,87 @@
+use std::sync::Arc;
+
+use color_eyre::eyre::Result;
+#[cfg(feature = "graphicscore")]
+use graphic_core::Render;
+use winit::window::Window;
+
+use crate::core::framebuffer::{FrameBuffer, PALETTE};
+
+/// Screen
+///
+/// `pixels` backed window surface, only ever presents a `FrameBuffer`.
+#[cfg(not(feature = "graphicscore"))]
+#[derive(Debug)]
+pub struct Screen {
+    p: pixels::Pixels,
+    /// size of the framebuffer the surface was last sized for
+    size: (usize, usize),
+}
+
+#[cfg(not(feature = "graphicscore"))]
+impl Screen {
+    pub fn new(window: &Arc<Window>, fb: &FrameBuffer) -> Result<Self> {
+        use pixels::{Pixels, SurfaceTexture};
+
+        let size = window.inner_size();
+        Ok(Screen {
+            p: Pixels::new(
+                fb.width() as u32,
+                fb.height() as u32,
+                SurfaceTexture::new(size.width, size.height, &**window),
+            )?,
+            size: (fb.width(), fb.height()),
+        })
+    }
+    pub fn resize(&mut self, width: u32, height: u32) -> bool {
+        self.p.resize_surface(width, height).is_ok()
+    }
+    /// copy the bit-planes into the surface and present it
+    pub fn render(&mut self, fb: &FrameBuffer) -> Result<()> {
+        // follow SUPER-CHIP resolution switches
+        if self.size != (fb.width(), fb.height()) {
+            self.p
+                .resize_buffer(fb.width() as u32, fb.height() as u32)?;
+            self.size = (fb.width(), fb.height());
+        }
+        let frame = self.p.frame_mut();
+        for (color, pixel) in fb.rows().flatten().zip(frame.chunks_exact_mut(4)) {
+            pixel.copy_from_slice(&PALETTE[*color as usize & 0b11]);
+        }
+        self.p.render()?;
+        Ok(())
+    }
+}
+
+/// Screen
+///
+/// `graphic-core` renderer, presents a `FrameBuffer` at a whole multiple
+/// of its size.
+#[cfg(feature = "graphicscore")]
+#[derive(Debug)]
+pub struct Screen {
+    renderer: graphic_core::Renderer,
+}
+
+#[cfg(feature = "graphicscore")]
+impl Screen {
+    pub fn new(window: &Arc<Window>, _fb: &FrameBuffer) -> Result<Self> {
+        Ok(Screen {
+            renderer: graphic_core::Renderer::new(window.clone())?,
+        })
+    }
+    pub fn resize(&mut self, width: u32, height: u32) -> bool {
+        self.renderer.resize(width, height);
+        true
+    }
+    /// hand the palette indices to the renderer, it follows size changes
+    pub fn render(&mut self, fb: &FrameBuffer) -> Result<()> {
+        let frame = graphic_core::Frame::indexed(
+            fb.width() as u32,
+            fb.height() as u32,
+            fb.pixels(),
+            &PALETTE,
+        );
+        self.renderer.render(&frame)
+    }
+}
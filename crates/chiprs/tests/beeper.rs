@@ -0,0 +1,74 @@
+//! the buzzer sounds for exactly as many frames as the sound timer is set to
+
+use audio_core::{Format, WavSink};
+use chiprs::core::{
+    asm::assemble,
+    beeper::{Beeper, Tone},
+    clock::Clock,
+    processor::RendererState,
+    rewind::Rewind,
+    Processor,
+};
+
+/// 100 samples a frame
+const RATE: u32 = 6000;
+
+/// beeps as `(first frame, frames long)`
+fn beeps(samples: &[i16]) -> Vec<(usize, usize)> {
+    let per_frame = (RATE / 60) as usize;
+    let mut beeps = vec![];
+    let mut start = None;
+    for (i, &s) in samples.iter().chain([&0]).enumerate() {
+        match (start, s != 0) {
+            (None, true) => start = Some(i),
+            (Some(st), false) => {
+                assert_eq!(
+                    (st % per_frame, i % per_frame),
+                    (0, 0),
+                    "beep off a frame edge"
+                );
+                beeps.push((st / per_frame, (i - st) / per_frame));
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    beeps
+}
+
+#[test]
+fn beep_lasts_the_sound_timer() {
+    let rom = assemble(
+        "       LD V0, 30
+                LD ST, V0
+                LD V1, 60
+                LD DT, V1
+        wait:   LD V1, DT
+                SE V1, 0
+                JP wait
+                LD V0, 6
+                LD ST, V0
+        end:    JP end",
+    )
+    .unwrap();
+    let path = std::env::temp_dir().join(format!("chiprs-beep-{}.wav", std::process::id()));
+    let sink = WavSink::create(&path, Format::mono(RATE)).unwrap();
+
+    let mut proc = Processor::with_rom(rom);
+    let mut state = RendererState {
+        clock: Clock::new(700),
+        rewind: Rewind::new(0),
+        beeper: Some(Beeper::new(Box::new(sink), Tone::default())),
+        ..Default::default()
+    };
+    for _ in 0..90 {
+        state.frame(&mut proc).unwrap();
+    }
+    state.beeper.as_mut().unwrap().flush().unwrap();
+
+    let mut wav = hound::WavReader::open(&path).unwrap();
+    let samples: Vec<i16> = wav.samples().map(|s| s.unwrap()).collect();
+    assert_eq!(samples.len(), 90 * 100);
+    assert_eq!(beeps(&samples), [(0, 30), (60, 6)]);
+    std::fs::remove_file(path).unwrap();
+}
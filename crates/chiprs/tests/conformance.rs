@@ -0,0 +1,139 @@
+//! conformance roms from `test-data/conformance`, assembled and run headless
+//!
+//! homemade extras beside the Timendus suite in `timendus.rs`. the self
+//! checking roms count failed checks in VE and end with EXIT, the screen
+//! they end on is compared with `<rom>.png`. `BLESS=1` rewrites the screens.
+
+use std::path::PathBuf;
+
+use chiprs::core::{
+    asm::assemble_file,
+    clock::Clock,
+    framebuffer::PALETTE,
+    quirks::{Platform, Quirks},
+    Processor,
+};
+use golden::{Golden, Image};
+
+fn dir() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../../test-data/conformance")
+}
+
+fn boot(name: &str, platform: Platform) -> Processor {
+    let rom = assemble_file(dir().join(format!("{name}.8o"))).unwrap();
+    Processor::with_platform(rom, platform)
+}
+
+/// run until the rom exits, at most `frames` frames, pressing `keys` as
+/// (key, first frame held, first frame released)
+fn run(proc: &mut Processor, frames: u64, keys: &[(u8, u64, u64)]) {
+    let mut clock = Clock::default();
+    for frame in 0..frames {
+        for &(key, down, up) in keys {
+            proc.set_key(key, (down..up).contains(&frame));
+        }
+        clock.frame(proc).unwrap();
+        if proc.halted() {
+            return;
+        }
+    }
+}
+
+fn check_screen(proc: &Processor, name: &str) {
+    let fb = proc.framebuffer();
+    let screen = Image::from_fn(fb.width() as u32, fb.height() as u32, |x, y| {
+        let [r, g, b, _] = PALETTE[fb.color(x as usize, y as usize) as usize & 0b11];
+        [r, g, b]
+    });
+    let golden = Golden::new(dir(), golden::bless_from_env());
+    if let Err(e) = golden.check_image(name, &screen) {
+        panic!("{e}");
+    }
+}
+
+/// the rom reached `pass` with no failed checks
+fn assert_passed(proc: &Processor) {
+    assert!(
+        proc.halted(),
+        "rom did not finish, stuck at {:#05X}",
+        proc.program_counter()
+    );
+    let failed = proc.registers()[0xE];
+    assert_eq!(
+        failed, 0,
+        "{failed} checks failed, the crosses on screen mark them"
+    );
+}
+
+#[test]
+fn ibm_logo() {
+    let mut proc = boot("ibm", Platform::Vip);
+    run(&mut proc, 10, &[]);
+    check_screen(&proc, "ibm");
+}
+
+#[test]
+fn opcodes() {
+    for platform in [
+        Platform::Vip,
+        Platform::Chip48,
+        Platform::Schip,
+        Platform::XoChip,
+    ] {
+        let mut proc = boot("opcodes", platform);
+        run(&mut proc, 300, &[]);
+        assert_passed(&proc);
+        check_screen(&proc, "opcodes");
+    }
+}
+
+#[test]
+fn flags() {
+    for platform in [
+        Platform::Vip,
+        Platform::Chip48,
+        Platform::Schip,
+        Platform::XoChip,
+    ] {
+        let mut proc = boot("flags", platform);
+        run(&mut proc, 300, &[]);
+        assert_passed(&proc);
+        check_screen(&proc, "flags");
+    }
+}
+
+#[test]
+fn quirks() {
+    for (platform, name) in [
+        (Platform::Vip, "quirks-vip"),
+        (Platform::Chip48, "quirks-chip48"),
+        (Platform::Schip, "quirks-schip"),
+        (Platform::XoChip, "quirks-xochip"),
+    ] {
+        let mut proc = boot("quirks", platform);
+        run(&mut proc, 60, &[]);
+        assert!(proc.halted(), "{name} did not finish");
+        let q: Quirks = platform.into();
+        let expected = q.vf_reset as u8
+            | (q.memory_increment as u8) << 1
+            | (q.shift_vy as u8) << 3
+            | (q.jump_vx as u8) << 4
+            | (q.display_wait as u8) << 5
+            | (q.wrap as u8) << 6;
+        assert_eq!(
+            proc.registers()[5],
+            expected,
+            "{name} detected {:#04X}",
+            proc.registers()[5]
+        );
+        check_screen(&proc, name);
+    }
+}
+
+#[test]
+fn keypad() {
+    let mut proc = boot("keypad", Platform::Vip);
+    run(&mut proc, 120, &[(0x5, 5, 10), (0x9, 20, 60)]);
+    assert_passed(&proc);
+    check_screen(&proc, "keypad");
+}
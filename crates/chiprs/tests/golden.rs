@@ -0,0 +1,111 @@
+//! golden screen and register tests, one case per `test-data/golden/chip8/*.toml`
+//!
+//! ```toml
+//! rom = "../../test.chip8.rom"  # or `source = "case.8o"` to assemble one
+//! frames = 60                   # frames to run headless before comparing
+//! ips = 700
+//! quirks = "vip"
+//! seed = 1                      # RND seed, 0 when left out
+//! registers = true              # also compare the registers with <case>.txt
+//! input = """                   # gamepad script, see input_core::ScriptedInput
+//! 10 south down
+//! """
+//! ```
+
+use std::{
+    fmt::Write,
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
+
+use chiprs::core::{
+    asm::assemble_file,
+    clock::{Clock, DEFAULT_IPS},
+    framebuffer::PALETTE,
+    processor::RendererState,
+    quirks::Platform,
+    rewind::Rewind,
+    rng::Rng,
+    Processor, Rom,
+};
+use clap::ValueEnum;
+use color_eyre::{
+    eyre::{bail, eyre},
+    Result,
+};
+use golden::{Golden, Harness, Image};
+use input_core::ScriptedInput;
+use serde::Deserialize;
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Case {
+    rom: Option<PathBuf>,
+    source: Option<PathBuf>,
+    frames: u64,
+    ips: Option<u32>,
+    quirks: Option<String>,
+    #[serde(default)]
+    seed: u64,
+    #[serde(default)]
+    registers: bool,
+    #[serde(default)]
+    input: String,
+}
+
+fn run(path: &Path, golden: &Golden) -> Result<()> {
+    let case: Case = toml::from_str(&std::fs::read_to_string(path)?)?;
+    let dir = path.parent().unwrap_or(Path::new("."));
+    let rom = match (&case.rom, &case.source) {
+        (Some(rom), None) => Rom::load_from_path(dir.join(rom))?,
+        (None, Some(source)) => assemble_file(dir.join(source))?,
+        _ => bail!("expected one of `rom` or `source`"),
+    };
+    let platform = match &case.quirks {
+        Some(name) => Platform::from_str(name, true).map_err(|e| eyre!(e))?,
+        None => Platform::default(),
+    };
+
+    let mut proc = Processor::with_platform(rom, platform);
+    proc.set_rng(Rng::seeded(case.seed));
+    let mut state = RendererState {
+        clock: Clock::new(case.ips.unwrap_or(DEFAULT_IPS)),
+        rewind: Rewind::new(0),
+        inputs: vec![Box::new(ScriptedInput::parse(&case.input)?)],
+        bindings: input_core::profile::chip8(),
+        ..Default::default()
+    };
+    for _ in 0..case.frames {
+        state.frame(&mut proc)?;
+    }
+
+    let name = path.file_stem().unwrap_or_default().to_string_lossy();
+    let fb = proc.framebuffer();
+    let screen = Image::from_fn(fb.width() as u32, fb.height() as u32, |x, y| {
+        let [r, g, b, _] = PALETTE[fb.color(x as usize, y as usize) as usize & 0b11];
+        [r, g, b]
+    });
+    golden.check_image(&name, &screen)?;
+    if case.registers {
+        golden.check_text(&name, &registers(&proc))?;
+    }
+    Ok(())
+}
+
+fn registers(proc: &Processor) -> String {
+    let mut out = String::new();
+    for (x, v) in proc.registers().iter().enumerate() {
+        _ = writeln!(out, "V{x:X} {v:02X}");
+    }
+    _ = writeln!(out, "I  {:04X}", proc.i());
+    _ = writeln!(out, "PC {:04X}", proc.program_counter());
+    _ = writeln!(out, "DT {:02X}", proc.delay_timer());
+    _ = writeln!(out, "ST {:02X}", proc.sound_timer());
+    _ = writeln!(out, "stack {:04X?}", proc.stack());
+    out
+}
+
+fn main() -> ExitCode {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("../../test-data/golden/chip8");
+    Harness::from_args().run(&dir, "toml", run)
+}
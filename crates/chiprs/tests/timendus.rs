@@ -0,0 +1,122 @@
+//! Timendus' chip8 test suite from `test-data/timendus`, run headless
+//!
+//! the roms never exit, they are run for a fixed number of frames and the
+//! screen they settle on is compared with `<rom>.png`. the roms are not
+//! checked in yet, see `test-data/timendus/README.md`; each test is skipped
+//! while its rom is missing. `BLESS=1` rewrites the screens.
+
+use std::path::PathBuf;
+
+use chiprs::core::{clock::Clock, framebuffer::PALETTE, quirks::Platform, Processor, Rom};
+use golden::{Golden, Image};
+
+/// where the suite roms read a menu choice from, skipping the menu
+const MENU_CHOICE: usize = 0x1FF;
+
+fn dir() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../../test-data/timendus")
+}
+
+/// `<name>.ch8` on `platform`, with `choice` preset at `MENU_CHOICE`.
+/// `None` when the rom is not there
+fn boot(name: &str, platform: Platform, choice: Option<u8>) -> Option<Processor> {
+    let path = dir().join(format!("{name}.ch8"));
+    if !path.exists() {
+        eprintln!("skipping {name}, {} is missing", path.display());
+        return None;
+    }
+    let mut proc = Processor::with_platform(Rom::load_from_path(path).unwrap(), platform);
+    if let Some(choice) = choice {
+        let mut s = proc.snapshot();
+        s.memory[MENU_CHOICE] = choice;
+        proc.restore(s);
+    }
+    Some(proc)
+}
+
+/// run `frames` frames, pressing `keys` as
+/// (key, first frame held, first frame released)
+fn run(proc: &mut Processor, frames: u64, keys: &[(u8, u64, u64)]) {
+    let mut clock = Clock::default();
+    for frame in 0..frames {
+        for &(key, down, up) in keys {
+            proc.set_key(key, (down..up).contains(&frame));
+        }
+        clock.frame(proc).unwrap();
+    }
+}
+
+fn check_screen(proc: &Processor, name: &str) {
+    let fb = proc.framebuffer();
+    let screen = Image::from_fn(fb.width() as u32, fb.height() as u32, |x, y| {
+        let [r, g, b, _] = PALETTE[fb.color(x as usize, y as usize) as usize & 0b11];
+        [r, g, b]
+    });
+    let golden = Golden::new(dir(), golden::bless_from_env());
+    if let Err(e) = golden.check_image(name, &screen) {
+        panic!("{e}");
+    }
+}
+
+#[test]
+fn chip8_logo() {
+    let Some(mut proc) = boot("1-chip8-logo", Platform::Vip, None) else {
+        return;
+    };
+    run(&mut proc, 60, &[]);
+    check_screen(&proc, "1-chip8-logo");
+}
+
+#[test]
+fn ibm_logo() {
+    let Some(mut proc) = boot("2-ibm-logo", Platform::Vip, None) else {
+        return;
+    };
+    run(&mut proc, 60, &[]);
+    check_screen(&proc, "2-ibm-logo");
+}
+
+#[test]
+fn corax_plus() {
+    let Some(mut proc) = boot("3-corax+", Platform::Vip, None) else {
+        return;
+    };
+    run(&mut proc, 120, &[]);
+    check_screen(&proc, "3-corax+");
+}
+
+#[test]
+fn flags() {
+    let Some(mut proc) = boot("4-flags", Platform::Vip, None) else {
+        return;
+    };
+    run(&mut proc, 300, &[]);
+    check_screen(&proc, "4-flags");
+}
+
+#[test]
+fn quirks() {
+    // menu entries of the quirks rom: CHIP-8, modern SUPER-CHIP, XO-CHIP
+    // and legacy SUPER-CHIP, which is what `Platform::Schip` models
+    for (platform, choice, name) in [
+        (Platform::Vip, 1, "5-quirks-vip"),
+        (Platform::Schip, 4, "5-quirks-schip"),
+        (Platform::XoChip, 3, "5-quirks-xochip"),
+    ] {
+        let Some(mut proc) = boot("5-quirks", platform, Some(choice)) else {
+            return;
+        };
+        run(&mut proc, 600, &[]);
+        check_screen(&proc, name);
+    }
+}
+
+#[test]
+fn keypad_get_key() {
+    // menu entry 3 is the Fx0A test, which passes once a key is released
+    let Some(mut proc) = boot("6-keypad", Platform::Vip, Some(3)) else {
+        return;
+    };
+    run(&mut proc, 120, &[(0x5, 30, 40)]);
+    check_screen(&proc, "6-keypad");
+}
@@ -0,0 +1,332 @@
+//! golden file regression tests for the emulator cores
+//!
+//! a core runs a rom headless and hands the final screen, and any state
+//! worth pinning down, to a `Golden` which compares them with the files
+//! checked in next to the test case. `--bless`, or `BLESS=1`, writes the
+//! current output as the new expectation instead.
+
+use std::{
+    fs::File,
+    io::{BufReader, BufWriter},
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
+
+use color_eyre::{
+    eyre::{bail, ensure},
+    Result,
+};
+
+/// `BLESS` is set, to anything but 0
+pub fn bless_from_env() -> bool {
+    std::env::var("BLESS").is_ok_and(|v| !v.is_empty() && v != "0")
+}
+
+/// Image
+///
+/// an 8-bit rgb screen capture, stored as png.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Image {
+    pub width: u32,
+    pub height: u32,
+    /// 3 bytes per pixel, row by row
+    pub rgb: Vec<u8>,
+}
+
+impl Image {
+    /// image with the color of each pixel from `f(x, y)`
+    pub fn from_fn(width: u32, height: u32, f: impl Fn(u32, u32) -> [u8; 3]) -> Self {
+        let rgb = (0..height)
+            .flat_map(|y| (0..width).map(move |x| (x, y)))
+            .flat_map(|(x, y)| f(x, y))
+            .collect();
+        Self { width, height, rgb }
+    }
+
+    pub fn pixel(&self, x: u32, y: u32) -> [u8; 3] {
+        let i = (y * self.width + x) as usize * 3;
+        [self.rgb[i], self.rgb[i + 1], self.rgb[i + 2]]
+    }
+
+    /// number of pixels that differ, `None` when the sizes do
+    pub fn diff(&self, other: &Image) -> Option<usize> {
+        if (self.width, self.height) != (other.width, other.height) {
+            return None;
+        }
+        let a = self.rgb.chunks_exact(3);
+        Some(
+            a.zip(other.rgb.chunks_exact(3))
+                .filter(|(a, b)| a != b)
+                .count(),
+        )
+    }
+
+    pub fn load_png(path: impl AsRef<Path>) -> Result<Self> {
+        let decoder = png::Decoder::new(BufReader::new(File::open(path)?));
+        let mut reader = decoder.read_info()?;
+        let mut buf = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut buf)?;
+        ensure!(
+            (info.color_type, info.bit_depth) == (png::ColorType::Rgb, png::BitDepth::Eight),
+            "expected an 8-bit rgb png, found {:?} {:?}",
+            info.color_type,
+            info.bit_depth
+        );
+        buf.truncate(info.buffer_size());
+        Ok(Self {
+            width: info.width,
+            height: info.height,
+            rgb: buf,
+        })
+    }
+
+    pub fn save_png(&self, path: impl AsRef<Path>) -> Result<()> {
+        let w = BufWriter::new(File::create(path)?);
+        let mut encoder = png::Encoder::new(w, self.width, self.height);
+        encoder.set_color(png::ColorType::Rgb);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder.write_header()?.write_image_data(&self.rgb)?;
+        Ok(())
+    }
+}
+
+/// Golden
+///
+/// expected outputs of one test case, `<name>.png` and `<name>.txt` in
+/// `dir`. on a mismatch the actual output is left beside them as
+/// `<name>.actual.png` or `<name>.actual.txt` for inspection.
+#[derive(Debug, Clone)]
+pub struct Golden {
+    dir: PathBuf,
+    bless: bool,
+}
+
+impl Golden {
+    pub fn new(dir: impl Into<PathBuf>, bless: bool) -> Self {
+        Self {
+            dir: dir.into(),
+            bless,
+        }
+    }
+
+    pub fn blessing(&self) -> bool {
+        self.bless
+    }
+
+    fn paths(&self, name: &str, ext: &str) -> (PathBuf, PathBuf) {
+        (
+            self.dir.join(format!("{name}.{ext}")),
+            self.dir.join(format!("{name}.actual.{ext}")),
+        )
+    }
+
+    /// compare `actual` with `<name>.png`
+    pub fn check_image(&self, name: &str, actual: &Image) -> Result<()> {
+        let (path, actual_path) = self.paths(name, "png");
+        _ = std::fs::remove_file(&actual_path);
+        if self.bless {
+            return actual.save_png(&path);
+        }
+        if !path.exists() {
+            bail!(
+                "{} is missing, bless it with --bless or BLESS=1",
+                path.display()
+            );
+        }
+        let expected = Image::load_png(&path)?;
+        let msg = match expected.diff(actual) {
+            Some(0) => return Ok(()),
+            Some(n) => format!("{n} pixels differ from {}", path.display()),
+            None => format!(
+                "{}x{} screen, {} is {}x{}",
+                actual.width,
+                actual.height,
+                path.display(),
+                expected.width,
+                expected.height
+            ),
+        };
+        actual.save_png(&actual_path)?;
+        bail!("{msg}, see {}", actual_path.display())
+    }
+
+    /// compare `actual` with `<name>.txt`
+    pub fn check_text(&self, name: &str, actual: &str) -> Result<()> {
+        let (path, actual_path) = self.paths(name, "txt");
+        _ = std::fs::remove_file(&actual_path);
+        if self.bless {
+            std::fs::write(&path, actual)?;
+            return Ok(());
+        }
+        if !path.exists() {
+            bail!(
+                "{} is missing, bless it with --bless or BLESS=1",
+                path.display()
+            );
+        }
+        let expected = std::fs::read_to_string(&path)?;
+        let Some((n, (e, a))) = expected
+            .lines()
+            .chain(std::iter::repeat(""))
+            .zip(actual.lines().chain(std::iter::repeat("")))
+            .take(expected.lines().count().max(actual.lines().count()))
+            .enumerate()
+            .find(|(_, (e, a))| e != a)
+        else {
+            return Ok(());
+        };
+        std::fs::write(&actual_path, actual)?;
+        bail!(
+            "{}:{} expected `{e}`, got `{a}`, see {}",
+            path.display(),
+            n + 1,
+            actual_path.display()
+        )
+    }
+}
+
+/// Harness
+///
+/// the `main` of a `harness = false` test target, runs a closure over
+/// every case file in a directory and reports like libtest.
+///
+/// ```text
+/// cargo test -p chiprs --test golden              # check
+/// cargo test -p chiprs --test golden -- --bless   # update the expectations
+/// cargo test -p chiprs --test golden -- maze      # only cases named *maze*
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Harness {
+    bless: bool,
+    filters: Vec<String>,
+}
+
+impl Harness {
+    /// `--bless` or a non-empty `BLESS`, other flags cargo passes along are
+    /// ignored and the remaining arguments filter cases by name
+    pub fn from_args() -> Self {
+        let mut harness = Self {
+            bless: bless_from_env(),
+            filters: vec![],
+        };
+        for arg in std::env::args().skip(1) {
+            match arg.as_str() {
+                "--bless" => harness.bless = true,
+                a if a.starts_with('-') => {}
+                _ => harness.filters.push(arg),
+            }
+        }
+        harness
+    }
+
+    /// run `case` on every `*.<ext>` file in `dir`, its expectations sit
+    /// beside it under the same file stem
+    pub fn run(
+        &self,
+        dir: &Path,
+        ext: &str,
+        case: impl Fn(&Path, &Golden) -> Result<()>,
+    ) -> ExitCode {
+        let mut cases: Vec<_> = match std::fs::read_dir(dir) {
+            Ok(entries) => entries
+                .filter_map(|e| Some(e.ok()?.path()))
+                .filter(|p| p.extension().is_some_and(|e| e == ext))
+                .collect(),
+            Err(e) => {
+                eprintln!("failed to read cases in {}: {e}", dir.display());
+                return ExitCode::FAILURE;
+            }
+        };
+        cases.sort();
+        let name = |p: &PathBuf| {
+            p.file_stem()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .into_owned()
+        };
+        cases.retain(|p| {
+            self.filters.is_empty() || self.filters.iter().any(|f| name(p).contains(f))
+        });
+
+        println!("\nrunning {} golden tests", cases.len());
+        let mut failed = vec![];
+        for path in &cases {
+            let golden = Golden::new(path.parent().unwrap_or(dir), self.bless);
+            match case(path, &golden) {
+                Ok(()) if self.bless => println!("test {} ... blessed", name(path)),
+                Ok(()) => println!("test {} ... ok", name(path)),
+                Err(e) => {
+                    println!("test {} ... FAILED", name(path));
+                    failed.push((name(path), e));
+                }
+            }
+        }
+        for (name, e) in &failed {
+            println!("\n---- {name} ----\n{e:#}");
+        }
+        let result = if failed.is_empty() { "ok" } else { "FAILED" };
+        println!(
+            "\ntest result: {result}. {} passed; {} failed\n",
+            cases.len() - failed.len(),
+            failed.len()
+        );
+        if failed.is_empty() {
+            ExitCode::SUCCESS
+        } else {
+            ExitCode::FAILURE
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scratch(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("golden-{name}-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn images_bless_then_match() {
+        let dir = scratch("image");
+        let checker = Image::from_fn(4, 2, |x, y| [((x + y) % 2 * 255) as u8; 3]);
+        Golden::new(&dir, true)
+            .check_image("board", &checker)
+            .unwrap();
+        assert_eq!(Image::load_png(dir.join("board.png")).unwrap(), checker);
+
+        let golden = Golden::new(&dir, false);
+        golden.check_image("board", &checker).unwrap();
+        let mut off = checker.clone();
+        off.rgb[0] = 7;
+        let err = golden.check_image("board", &off).unwrap_err();
+        assert!(err.to_string().starts_with("1 pixels differ"), "{err}");
+        assert!(dir.join("board.actual.png").exists());
+        assert!(golden.check_image("missing", &off).is_err());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn text_mismatch_names_the_line() {
+        let dir = scratch("text");
+        let golden = Golden::new(&dir, false);
+        Golden::new(&dir, true)
+            .check_text("regs", "V0 01\nV1 02\n")
+            .unwrap();
+        golden.check_text("regs", "V0 01\nV1 02\n").unwrap();
+        let err = golden.check_text("regs", "V0 01\nV1 03\n").unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("regs.txt:2 expected `V1 02`, got `V1 03`"),
+            "{err}"
+        );
+        let err = golden.check_text("regs", "V0 01\n").unwrap_err();
+        assert!(
+            err.to_string().contains(":2 expected `V1 02`, got ``"),
+            "{err}"
+        );
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,94 @@
+use color_eyre::{eyre::ensure, Result};
+
+/// pixel data of a `Frame`, row by row from the top left
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Pixels<'a> {
+    /// a byte per pixel indexing `palette`, indices past its end are black
+    Indexed {
+        indices: &'a [u8],
+        palette: &'a [[u8; 4]],
+    },
+    /// 4 bytes per pixel, red, green, blue and alpha
+    Rgba(&'a [u8]),
+}
+
+/// Frame
+///
+/// one emulated screen, borrowed from the core for as long as it is drawn.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Frame<'a> {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Pixels<'a>,
+}
+
+impl<'a> Frame<'a> {
+    pub fn indexed(width: u32, height: u32, indices: &'a [u8], palette: &'a [[u8; 4]]) -> Self {
+        Self {
+            width,
+            height,
+            pixels: Pixels::Indexed { indices, palette },
+        }
+    }
+
+    pub fn rgba(width: u32, height: u32, rgba: &'a [u8]) -> Self {
+        Self {
+            width,
+            height,
+            pixels: Pixels::Rgba(rgba),
+        }
+    }
+
+    /// replace `out` with the frame as rgba
+    pub fn to_rgba(&self, out: &mut Vec<u8>) -> Result<()> {
+        let n = self.width as usize * self.height as usize;
+        out.clear();
+        match self.pixels {
+            Pixels::Indexed { indices, palette } => {
+                ensure!(
+                    indices.len() == n,
+                    "{}x{} frame with {} palette indices",
+                    self.width,
+                    self.height,
+                    indices.len()
+                );
+                out.extend(
+                    indices
+                        .iter()
+                        .flat_map(|&i| palette.get(i as usize).unwrap_or(&[0, 0, 0, 0xFF])),
+                );
+            }
+            Pixels::Rgba(rgba) => {
+                ensure!(
+                    rgba.len() == n * 4,
+                    "{}x{} frame with {} bytes of rgba",
+                    self.width,
+                    self.height,
+                    rgba.len()
+                );
+                out.extend_from_slice(rgba);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn indexed_frames_look_up_the_palette() {
+        let palette = [[1, 2, 3, 4], [5, 6, 7, 8]];
+        let mut out = vec![];
+        Frame::indexed(3, 1, &[1, 0, 9], &palette)
+            .to_rgba(&mut out)
+            .unwrap();
+        assert_eq!(out, [5, 6, 7, 8, 1, 2, 3, 4, 0, 0, 0, 0xFF]);
+
+        assert!(Frame::indexed(2, 2, &[0; 3], &palette)
+            .to_rgba(&mut out)
+            .is_err());
+        assert!(Frame::rgba(1, 1, &[0; 3]).to_rgba(&mut out).is_err());
+    }
+}
@@ -1 +1,39 @@
-// softbuffer
+//! presents emulator framebuffers
+//!
+//! a core hands over a `Frame`, palette indices or rgba of any size, and a
+//! `Render` backend scales it as its `Options` say, the borders are black.
+//! the `Renderer` draws into a window's wgpu surface, the `SoftwareRenderer`
+//! into an rgba buffer or png on machines without a gpu. the `wgpu`
+//! feature, on by default, builds the `Renderer` and its gpu stack.
+
+pub mod frame;
+pub mod options;
+#[cfg(feature = "wgpu")]
+pub mod renderer;
+pub mod scale;
+pub mod software;
+
+use color_eyre::Result;
+
+pub use frame::{Frame, Pixels};
+pub use options::{Options, PostFilters, Sampling};
+#[cfg(feature = "wgpu")]
+pub use renderer::Renderer;
+pub use scale::{Scaling, Viewport};
+pub use software::SoftwareRenderer;
+
+/// Render
+///
+/// a backend presenting frames. both sample and filter in linear light,
+/// so they agree short of rounding.
+pub trait Render {
+    fn options(&self) -> &Options;
+
+    /// changes take effect from the next `render`
+    fn options_mut(&mut self) -> &mut Options;
+
+    /// the output is now `width` x `height` pixels
+    fn resize(&mut self, width: u32, height: u32);
+
+    fn render(&mut self, frame: &Frame) -> Result<()>;
+}
@@ -0,0 +1,41 @@
+use crate::Scaling;
+
+/// how frame pixels are read between their centres
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Sampling {
+    /// the closest pixel, sharp edges
+    #[default]
+    Nearest,
+    /// the 4 closest pixels blended
+    Bilinear,
+}
+
+/// effects applied to the scaled frame
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct PostFilters {
+    /// darken the lower half of every emulated row by this much, 0.0 is off
+    pub scanlines: f32,
+    /// show the luma only
+    pub grayscale: bool,
+}
+
+/// how a `Render` backend presents frames
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Options {
+    pub scaling: Scaling,
+    pub sampling: Sampling,
+    /// width over height of an emulated pixel, 8/7 for the NES say
+    pub pixel_aspect: f32,
+    pub post: PostFilters,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            scaling: Scaling::default(),
+            sampling: Sampling::default(),
+            pixel_aspect: 1.0,
+            post: PostFilters::default(),
+        }
+    }
+}
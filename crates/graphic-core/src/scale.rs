@@ -0,0 +1,94 @@
+/// how a frame is fitted into the window
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Scaling {
+    /// the largest whole multiple of the frame that fits, every emulated
+    /// pixel the same size. falls back to `Fit` in windows smaller than
+    /// the frame
+    #[default]
+    Integer,
+    /// as large as fits, keeping the aspect ratio
+    Fit,
+    /// the whole window, whatever the aspect ratio
+    Stretch,
+}
+
+/// area of the window a frame is drawn in, in physical pixels
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Viewport {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Scaling {
+    /// where a `frame` sized screen goes in a `surface` sized window,
+    /// centred. `pixel_aspect` is the width of an emulated pixel over its
+    /// height, 8/7 for the NES say.
+    pub fn viewport(self, frame: (u32, u32), surface: (u32, u32), pixel_aspect: f32) -> Viewport {
+        let (sw, sh) = surface;
+        if self == Scaling::Stretch || frame.0 == 0 || frame.1 == 0 {
+            return centred(sw, sh, surface);
+        }
+        // the frame's size on a display, before scaling
+        let (fw, fh) = (frame.0 as f32 * pixel_aspect, frame.1 as f32);
+        let fit = (sw as f32 / fw).min(sh as f32 / fh);
+        let scale = match self {
+            Scaling::Integer if fit >= 1.0 => fit.floor(),
+            _ => fit,
+        };
+        let w = ((fw * scale).round() as u32).min(sw);
+        let h = ((fh * scale).round() as u32).min(sh);
+        centred(w, h, surface)
+    }
+}
+
+fn centred(width: u32, height: u32, (sw, sh): (u32, u32)) -> Viewport {
+    Viewport {
+        x: (sw - width) / 2,
+        y: (sh - height) / 2,
+        width,
+        height,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn size(v: Viewport) -> (u32, u32, u32, u32) {
+        (v.x, v.y, v.width, v.height)
+    }
+
+    #[test]
+    fn scaling_modes() {
+        let chip8 = (64, 32);
+        let window = (800, 600);
+        assert_eq!(
+            size(Scaling::Integer.viewport(chip8, window, 1.0)),
+            (16, 108, 768, 384)
+        );
+        assert_eq!(
+            size(Scaling::Fit.viewport(chip8, window, 1.0)),
+            (0, 100, 800, 400)
+        );
+        assert_eq!(
+            size(Scaling::Stretch.viewport(chip8, window, 1.0)),
+            (0, 0, 800, 600)
+        );
+        // too small for even one whole multiple
+        assert_eq!(
+            size(Scaling::Integer.viewport(chip8, (48, 48), 1.0)),
+            (0, 12, 48, 24)
+        );
+    }
+
+    #[test]
+    fn pixel_aspect_widens_the_frame() {
+        let nes = (256, 240);
+        let v = Scaling::Integer.viewport(nes, (1920, 1080), 8.0 / 7.0);
+        assert_eq!(size(v), (375, 60, 1170, 960));
+        let v = Scaling::Fit.viewport(nes, (1920, 1080), 8.0 / 7.0);
+        assert_eq!((v.width, v.height), (1317, 1080));
+    }
+}
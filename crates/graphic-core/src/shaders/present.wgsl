@@ -0,0 +1,46 @@
+// draws the frame texture over the whole viewport with one triangle
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+};
+
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> VertexOutput {
+    // (0, 0), (2, 0) and (0, 2), the viewport is the top left quarter
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+    var out: VertexOutput;
+    out.position = vec4<f32>(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0, 0.0, 1.0);
+    out.uv = uv;
+    return out;
+}
+
+@group(0) @binding(0)
+var frame: texture_2d<f32>;
+@group(0) @binding(1)
+var frame_sampler: sampler;
+
+struct Post {
+    // darkening of the lower half of each emulated row
+    scanlines: f32,
+    // 1.0 shows the luma only
+    grayscale: f32,
+    // emulated rows in the frame
+    rows: f32,
+    _pad: f32,
+};
+
+@group(0) @binding(2)
+var<uniform> post: Post;
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    var color = textureSample(frame, frame_sampler, in.uv);
+    if post.grayscale > 0.5 {
+        color = vec4<f32>(vec3<f32>(dot(color.rgb, vec3<f32>(0.299, 0.587, 0.114))), color.a);
+    }
+    if fract(in.uv.y * post.rows) >= 0.5 {
+        color = vec4<f32>(color.rgb * (1.0 - post.scanlines), color.a);
+    }
+    return color;
+}
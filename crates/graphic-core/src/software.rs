@@ -0,0 +1,277 @@
+use std::{fs::File, io::BufWriter, io::Write, path::Path};
+
+use color_eyre::Result;
+
+use crate::{Frame, Options, Render, Sampling, Viewport};
+
+/// SoftwareRenderer
+///
+/// rasterizes frames on the cpu into an rgba image, for screenshots and
+/// visual tests on machines without a gpu. like the gpu with its srgb
+/// texture it samples and filters in linear light.
+#[derive(Debug, Clone)]
+pub struct SoftwareRenderer {
+    width: u32,
+    height: u32,
+    options: Options,
+    /// the last frame as rgba
+    src: Vec<u8>,
+    /// the last frame in linear light, 0.0 to 1.0
+    linear: Vec<[f32; 4]>,
+    /// the output, 4 bytes a pixel row by row
+    rgba: Vec<u8>,
+}
+
+impl SoftwareRenderer {
+    /// black `width` x `height` output
+    pub fn new(width: u32, height: u32) -> Self {
+        let mut renderer = Self {
+            width,
+            height,
+            options: Options::default(),
+            src: vec![],
+            linear: vec![],
+            rgba: vec![],
+        };
+        renderer.resize(width, height);
+        renderer
+    }
+
+    pub fn size(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    /// the last render, 4 bytes a pixel
+    pub fn rgba(&self) -> &[u8] {
+        &self.rgba
+    }
+
+    pub fn pixel(&self, x: u32, y: u32) -> [u8; 4] {
+        let i = (y * self.width + x) as usize * 4;
+        [
+            self.rgba[i],
+            self.rgba[i + 1],
+            self.rgba[i + 2],
+            self.rgba[i + 3],
+        ]
+    }
+
+    /// encode the last render as an rgba png
+    pub fn write_png(&self, w: impl Write) -> Result<()> {
+        let mut encoder = png::Encoder::new(w, self.width, self.height);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder.write_header()?.write_image_data(&self.rgba)?;
+        Ok(())
+    }
+
+    pub fn save_png(&self, path: impl AsRef<Path>) -> Result<()> {
+        self.write_png(BufWriter::new(File::create(path)?))
+    }
+
+    /// color of the frame at `(u, v)`, in source pixels from its top left
+    fn sample(&self, frame: (u32, u32), u: f32, v: f32) -> [f32; 4] {
+        let (fw, fh) = frame;
+        let texel = |x: i64, y: i64| {
+            let x = x.clamp(0, fw as i64 - 1) as usize;
+            let y = y.clamp(0, fh as i64 - 1) as usize;
+            self.linear[y * fw as usize + x]
+        };
+        match self.options.sampling {
+            Sampling::Nearest => texel(u.floor() as i64, v.floor() as i64),
+            Sampling::Bilinear => {
+                // between the centres of the 4 closest pixels
+                let (u, v) = (u - 0.5, v - 0.5);
+                let (x, y) = (u.floor(), v.floor());
+                let (tx, ty) = (u - x, v - y);
+                let (x, y) = (x as i64, y as i64);
+                let lerp = |a: [f32; 4], b: [f32; 4], t: f32| {
+                    [0, 1, 2, 3].map(|c| a[c] + (b[c] - a[c]) * t)
+                };
+                let top = lerp(texel(x, y), texel(x + 1, y), tx);
+                let bottom = lerp(texel(x, y + 1), texel(x + 1, y + 1), tx);
+                lerp(top, bottom, ty)
+            }
+        }
+    }
+}
+
+impl Render for SoftwareRenderer {
+    fn options(&self) -> &Options {
+        &self.options
+    }
+
+    fn options_mut(&mut self) -> &mut Options {
+        &mut self.options
+    }
+
+    fn resize(&mut self, width: u32, height: u32) {
+        (self.width, self.height) = (width, height);
+        self.rgba.clear();
+        self.rgba
+            .extend([0, 0, 0, 0xFF].repeat(width as usize * height as usize));
+    }
+
+    fn render(&mut self, frame: &Frame) -> Result<()> {
+        frame.to_rgba(&mut self.src)?;
+        let lut: [f32; 256] = std::array::from_fn(|c| to_linear(c as u8));
+        self.linear.clear();
+        self.linear.extend(self.src.chunks_exact(4).map(|p| {
+            [
+                lut[p[0] as usize],
+                lut[p[1] as usize],
+                lut[p[2] as usize],
+                p[3] as f32 / 255.0,
+            ]
+        }));
+        for p in self.rgba.chunks_exact_mut(4) {
+            p.copy_from_slice(&[0, 0, 0, 0xFF]);
+        }
+        let size = (frame.width, frame.height);
+        if size.0 == 0 || size.1 == 0 {
+            return Ok(());
+        }
+        let Options {
+            scaling,
+            pixel_aspect,
+            post,
+            ..
+        } = self.options;
+        let Viewport {
+            x,
+            y,
+            width,
+            height,
+        } = scaling.viewport(size, (self.width, self.height), pixel_aspect);
+        let (sx, sy) = (size.0 as f32 / width as f32, size.1 as f32 / height as f32);
+        for oy in y..y + height {
+            // sample at the centre of each output pixel
+            let v = (oy - y) as f32 * sy + sy / 2.0;
+            let dim = match v.fract() >= 0.5 {
+                true => 1.0 - post.scanlines,
+                false => 1.0,
+            };
+            for ox in x..x + width {
+                let u = (ox - x) as f32 * sx + sx / 2.0;
+                let [r, g, b, a] = self.sample(size, u, v);
+                let [r, g, b] = match post.grayscale {
+                    true => [0.299 * r + 0.587 * g + 0.114 * b; 3],
+                    false => [r, g, b],
+                };
+                let i = (oy * self.width + ox) as usize * 4;
+                self.rgba[i..i + 4].copy_from_slice(&[
+                    to_srgb(r * dim),
+                    to_srgb(g * dim),
+                    to_srgb(b * dim),
+                    (a * 255.0).round().clamp(0.0, 255.0) as u8,
+                ]);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// srgb encoded channel to linear light
+fn to_linear(c: u8) -> f32 {
+    let c = c as f32 / 255.0;
+    if c <= 0.04045 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+/// linear light back to an srgb encoded channel
+fn to_srgb(l: f32) -> u8 {
+    let c = if l <= 0.0031308 {
+        l * 12.92
+    } else {
+        1.055 * l.powf(1.0 / 2.4) - 0.055
+    };
+    (c * 255.0).round().clamp(0.0, 255.0) as u8
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{PostFilters, Scaling};
+
+    const PALETTE: [[u8; 4]; 2] = [[0, 0, 0, 0xFF], [200, 100, 40, 0xFF]];
+
+    /// a 2x1 frame, unlit then lit
+    fn frame() -> Frame<'static> {
+        Frame::indexed(2, 1, &[0, 1], &PALETTE)
+    }
+
+    #[test]
+    fn nearest_integer_scaling_letterboxes() {
+        let mut r = SoftwareRenderer::new(8, 6);
+        r.render(&frame()).unwrap();
+        // 4x scale, 8x4 centred with a row of border above and below
+        assert_eq!(r.pixel(0, 0), [0, 0, 0, 0xFF]);
+        assert_eq!(r.pixel(3, 1), PALETTE[0]);
+        assert_eq!(r.pixel(4, 1), PALETTE[1]);
+        assert_eq!(r.pixel(7, 4), PALETTE[1]);
+
+        r.options_mut().scaling = Scaling::Stretch;
+        r.render(&frame()).unwrap();
+        assert_eq!(r.pixel(7, 0), PALETTE[1]);
+        assert_eq!(r.pixel(7, 5), PALETTE[1]);
+    }
+
+    #[test]
+    fn bilinear_blends_between_centres() {
+        let mut r = SoftwareRenderer::new(4, 1);
+        r.options_mut().scaling = Scaling::Stretch;
+        r.options_mut().sampling = Sampling::Bilinear;
+        r.render(&frame()).unwrap();
+        let red: Vec<u8> = (0..4).map(|x| r.pixel(x, 0)[0]).collect();
+        // source centres fall between output pixels 0/1 and 2/3, blended
+        // in linear light as the gpu's srgb texture is
+        let lit = to_linear(200);
+        assert_eq!(red, [0, to_srgb(lit / 4.0), to_srgb(lit * 0.75), 200]);
+        assert_eq!(red, [0, 106, 176, 200]);
+    }
+
+    #[test]
+    fn post_filters() {
+        let mut r = SoftwareRenderer::new(2, 2);
+        r.options_mut().scaling = Scaling::Stretch;
+        r.options_mut().post = PostFilters {
+            scanlines: 0.5,
+            grayscale: true,
+        };
+        r.render(&frame()).unwrap();
+        let [red, green, blue, _] = PALETTE[1].map(to_linear);
+        let luma = 0.299 * red + 0.587 * green + 0.114 * blue;
+        let (lit, dim) = (to_srgb(luma), to_srgb(luma / 2.0));
+        assert_eq!(r.pixel(1, 0), [lit, lit, lit, 0xFF]);
+        assert_eq!(r.pixel(1, 1), [dim, dim, dim, 0xFF]);
+    }
+
+    #[test]
+    fn scanlines_darken_in_linear_light() {
+        let gray = [[200, 200, 200, 0xFF]];
+        let mut r = SoftwareRenderer::new(1, 2);
+        r.options_mut().scaling = Scaling::Stretch;
+        r.options_mut().post.scanlines = 0.5;
+        r.render(&Frame::indexed(1, 1, &[0], &gray)).unwrap();
+        // what the window shows, half the light of 200 encodes as 146 not 100
+        assert_eq!(r.pixel(0, 0), [200, 200, 200, 0xFF]);
+        assert_eq!(r.pixel(0, 1), [146, 146, 146, 0xFF]);
+    }
+
+    #[test]
+    fn png_round_trip() {
+        let mut r = SoftwareRenderer::new(6, 3);
+        r.render(&frame()).unwrap();
+        let mut png = vec![];
+        r.write_png(&mut png).unwrap();
+
+        let mut reader = png::Decoder::new(png.as_slice()).read_info().unwrap();
+        let mut buf = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut buf).unwrap();
+        assert_eq!((info.width, info.height), (6, 3));
+        assert_eq!(&buf[..info.buffer_size()], r.rgba());
+    }
+}
@@ -0,0 +1,216 @@
+use std::{collections::HashMap, fmt, path::Path};
+
+use color_eyre::{eyre::ContextCompat, Result};
+use evdev::{AbsoluteAxisCode, Device, EventSummary, KeyCode};
+use tracing::info;
+
+use super::{Button, Event, InputSource};
+
+/// gamepad button for an evdev key code
+fn button(code: KeyCode) -> Option<Button> {
+    Some(match code {
+        KeyCode::BTN_SOUTH => Button::South,
+        KeyCode::BTN_EAST => Button::East,
+        KeyCode::BTN_NORTH => Button::North,
+        KeyCode::BTN_WEST => Button::West,
+        KeyCode::BTN_DPAD_UP => Button::DPadUp,
+        KeyCode::BTN_DPAD_DOWN => Button::DPadDown,
+        KeyCode::BTN_DPAD_LEFT => Button::DPadLeft,
+        KeyCode::BTN_DPAD_RIGHT => Button::DPadRight,
+        KeyCode::BTN_START => Button::Start,
+        KeyCode::BTN_SELECT => Button::Select,
+        KeyCode::BTN_MODE => Button::Mode,
+        KeyCode::BTN_TL => Button::LeftShoulder,
+        KeyCode::BTN_TR => Button::RightShoulder,
+        KeyCode::BTN_TL2 => Button::LeftTrigger,
+        KeyCode::BTN_TR2 => Button::RightTrigger,
+        KeyCode::BTN_THUMBL => Button::LeftThumb,
+        KeyCode::BTN_THUMBR => Button::RightThumb,
+        _ => return None,
+    })
+}
+
+/// Axes
+///
+/// turns the hat and left stick into dpad presses, a stick counts as
+/// pushed more than half way out from centre.
+#[derive(Debug, Default, Clone)]
+struct Axes {
+    /// (min, max) of each axis reported by the device
+    ranges: HashMap<AbsoluteAxisCode, (i32, i32)>,
+    /// direction each axis is held in, -1, 0 or 1
+    held: HashMap<AbsoluteAxisCode, i8>,
+}
+
+impl Axes {
+    /// dpad events for `axis` moving to `value`
+    fn update(&mut self, axis: AbsoluteAxisCode, value: i32) -> Vec<Event> {
+        let (neg, pos) = match axis {
+            AbsoluteAxisCode::ABS_HAT0X | AbsoluteAxisCode::ABS_X => {
+                (Button::DPadLeft, Button::DPadRight)
+            }
+            AbsoluteAxisCode::ABS_HAT0Y | AbsoluteAxisCode::ABS_Y => {
+                (Button::DPadUp, Button::DPadDown)
+            }
+            _ => return vec![],
+        };
+        let (min, max) = self.ranges.get(&axis).copied().unwrap_or((-1, 1));
+        let (center, reach) = ((min + max) / 2, (max - min) / 4);
+        let dir = if value > center + reach {
+            1
+        } else if value < center - reach {
+            -1
+        } else {
+            0
+        };
+        let old = self.held.insert(axis, dir).unwrap_or(0);
+        let mut events = vec![];
+        match old {
+            -1 if dir != -1 => events.push(Event::release(neg)),
+            1 if dir != 1 => events.push(Event::release(pos)),
+            _ => {}
+        }
+        match dir {
+            -1 if old != -1 => events.push(Event::press(neg)),
+            1 if old != 1 => events.push(Event::press(pos)),
+            _ => {}
+        }
+        events
+    }
+}
+
+/// EvdevGamepad
+///
+/// a linux gamepad read through `/dev/input/event*`, needs read access to
+/// the device, usually through the `input` group.
+pub struct EvdevGamepad {
+    device: Device,
+    axes: Axes,
+}
+
+impl fmt::Debug for EvdevGamepad {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EvdevGamepad")
+            .field("name", &self.device.name())
+            .field("axes", &self.axes)
+            .finish()
+    }
+}
+
+impl EvdevGamepad {
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        Self::from_device(Device::open(path)?)
+    }
+
+    /// the first device with gamepad buttons
+    pub fn first() -> Result<Self> {
+        let (path, device) = evdev::enumerate()
+            .find(|(_, d)| Self::is_gamepad(d))
+            .context("no gamepad found under /dev/input")?;
+        info!("using gamepad {:?} at {}", device.name(), path.display());
+        Self::from_device(device)
+    }
+
+    fn is_gamepad(device: &Device) -> bool {
+        device
+            .supported_keys()
+            .is_some_and(|k| k.contains(KeyCode::BTN_SOUTH))
+    }
+
+    fn from_device(device: Device) -> Result<Self> {
+        device.set_nonblocking(true)?;
+        let ranges = device
+            .get_absinfo()?
+            .map(|(axis, info)| (axis, (info.minimum(), info.maximum())))
+            .collect();
+        Ok(Self {
+            device,
+            axes: Axes {
+                ranges,
+                ..Default::default()
+            },
+        })
+    }
+}
+
+impl InputSource for EvdevGamepad {
+    fn poll(&mut self) -> Result<Vec<Event>> {
+        let events = match self.device.fetch_events() {
+            Ok(events) => events,
+            Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => return Ok(vec![]),
+            Err(e) => return Err(e.into()),
+        };
+        let mut out = vec![];
+        for e in events {
+            match e.destructure() {
+                // 2 is autorepeat, the button is already down
+                EventSummary::Key(_, code, value @ (0 | 1)) => {
+                    if let Some(button) = button(code) {
+                        out.push(Event {
+                            button,
+                            pressed: value == 1,
+                        });
+                    }
+                }
+                EventSummary::AbsoluteAxis(_, axis, value) => {
+                    out.extend(self.axes.update(axis, value));
+                }
+                _ => {}
+            }
+        }
+        Ok(out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn axes_press_and_release_the_dpad() {
+        let mut axes = Axes::default();
+        axes.ranges.insert(AbsoluteAxisCode::ABS_X, (0, 255));
+        let x = AbsoluteAxisCode::ABS_X;
+        assert_eq!(axes.update(x, 130), []);
+        assert_eq!(axes.update(x, 250), [Event::press(Button::DPadRight)]);
+        assert_eq!(axes.update(x, 240), []);
+        assert_eq!(
+            axes.update(x, 2),
+            [
+                Event::release(Button::DPadRight),
+                Event::press(Button::DPadLeft)
+            ]
+        );
+        let hat = AbsoluteAxisCode::ABS_HAT0Y;
+        assert_eq!(axes.update(hat, -1), [Event::press(Button::DPadUp)]);
+        assert_eq!(axes.update(hat, 0), [Event::release(Button::DPadUp)]);
+    }
+
+    /// creates a virtual pad, so it needs write access to /dev/uinput
+    #[test]
+    #[ignore]
+    fn reads_a_virtual_uinput_pad() {
+        use evdev::{uinput::VirtualDevice, AttributeSet, InputEvent};
+
+        let keys: AttributeSet<KeyCode> = [KeyCode::BTN_SOUTH, KeyCode::BTN_START]
+            .into_iter()
+            .collect();
+        let mut virt = VirtualDevice::builder()
+            .unwrap()
+            .name("lemu test pad")
+            .with_keys(&keys)
+            .unwrap()
+            .build()
+            .unwrap();
+        // udev needs a moment to create the node
+        std::thread::sleep(std::time::Duration::from_millis(200));
+        let path = virt.enumerate_dev_nodes_blocking().unwrap().next().unwrap();
+        let mut pad = EvdevGamepad::open(path.unwrap()).unwrap();
+
+        let press = InputEvent::new(evdev::EventType::KEY.0, KeyCode::BTN_SOUTH.0, 1);
+        virt.emit(&[press]).unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(50));
+        assert_eq!(pad.poll().unwrap(), [Event::press(Button::South)]);
+        assert_eq!(pad.poll().unwrap(), []);
+    }
+}
@@ -0,0 +1,128 @@
+//! controller input shared by the emulator frontends
+//!
+//! sources report presses of a generic gamepad `Button`, a per-core
+//! `Bindings` profile turns them into keypad keys or joypad buttons.
+
+#[cfg(all(target_os = "linux", feature = "evdev"))]
+pub mod gamepad;
+pub mod profile;
+pub mod script;
+
+use std::{fmt::Debug, str::FromStr};
+
+use color_eyre::{eyre::eyre, Result};
+
+pub use profile::Bindings;
+pub use script::ScriptedInput;
+
+/// buttons of a standard gamepad, named by position
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Button {
+    /// bottom face button, A on xbox pads, cross on playstation ones
+    South,
+    East,
+    North,
+    West,
+    DPadUp,
+    DPadDown,
+    DPadLeft,
+    DPadRight,
+    Start,
+    Select,
+    /// the guide or home button
+    Mode,
+    LeftShoulder,
+    RightShoulder,
+    LeftTrigger,
+    RightTrigger,
+    LeftThumb,
+    RightThumb,
+}
+
+impl Button {
+    pub const ALL: [Button; 17] = [
+        Button::South,
+        Button::East,
+        Button::North,
+        Button::West,
+        Button::DPadUp,
+        Button::DPadDown,
+        Button::DPadLeft,
+        Button::DPadRight,
+        Button::Start,
+        Button::Select,
+        Button::Mode,
+        Button::LeftShoulder,
+        Button::RightShoulder,
+        Button::LeftTrigger,
+        Button::RightTrigger,
+        Button::LeftThumb,
+        Button::RightThumb,
+    ];
+
+    /// snake case name used by scripts and binding files
+    pub fn name(&self) -> &'static str {
+        match self {
+            Button::South => "south",
+            Button::East => "east",
+            Button::North => "north",
+            Button::West => "west",
+            Button::DPadUp => "dpad_up",
+            Button::DPadDown => "dpad_down",
+            Button::DPadLeft => "dpad_left",
+            Button::DPadRight => "dpad_right",
+            Button::Start => "start",
+            Button::Select => "select",
+            Button::Mode => "mode",
+            Button::LeftShoulder => "left_shoulder",
+            Button::RightShoulder => "right_shoulder",
+            Button::LeftTrigger => "left_trigger",
+            Button::RightTrigger => "right_trigger",
+            Button::LeftThumb => "left_thumb",
+            Button::RightThumb => "right_thumb",
+        }
+    }
+}
+
+impl FromStr for Button {
+    type Err = color_eyre::Report;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Button::ALL
+            .into_iter()
+            .find(|b| b.name().eq_ignore_ascii_case(s))
+            .ok_or_else(|| eyre!("unknown button `{s}`"))
+    }
+}
+
+/// a button going down or coming back up
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Event {
+    pub button: Button,
+    pub pressed: bool,
+}
+
+impl Event {
+    pub fn press(button: Button) -> Self {
+        Self {
+            button,
+            pressed: true,
+        }
+    }
+
+    pub fn release(button: Button) -> Self {
+        Self {
+            button,
+            pressed: false,
+        }
+    }
+}
+
+/// InputSource
+///
+/// anything producing button events, polled once per emulated frame so
+/// scripted sources stay frame exact. `poll` never blocks.
+pub trait InputSource: Debug {
+    /// events since the last poll, oldest first
+    fn poll(&mut self) -> Result<Vec<Event>>;
+}
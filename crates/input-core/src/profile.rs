@@ -0,0 +1,216 @@
+use std::collections::HashMap;
+
+use super::{Button, Event};
+
+/// Bindings
+///
+/// what each gamepad button does in one core, a button is bound to at
+/// most one target but a target can have several buttons.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Bindings<T> {
+    map: HashMap<Button, T>,
+}
+
+impl<T: Copy> Bindings<T> {
+    pub fn new(pairs: impl IntoIterator<Item = (Button, T)>) -> Self {
+        Self {
+            map: pairs.into_iter().collect(),
+        }
+    }
+
+    /// bind `button` to `target`, replacing what it was bound to
+    pub fn bind(&mut self, button: Button, target: T) {
+        self.map.insert(button, target);
+    }
+
+    pub fn unbind(&mut self, button: Button) {
+        self.map.remove(&button);
+    }
+
+    pub fn get(&self, button: Button) -> Option<T> {
+        self.map.get(&button).copied()
+    }
+
+    /// call `f` with the target and state of every bound event
+    pub fn apply(&self, events: &[Event], mut f: impl FnMut(T, bool)) {
+        for e in events {
+            if let Some(target) = self.get(e.button) {
+                f(target, e.pressed);
+            }
+        }
+    }
+}
+
+/// chip8 hex keypad keys, most games move with 5/7/8/9 and act with 4/6
+pub fn chip8() -> Bindings<u8> {
+    Bindings::new([
+        (Button::DPadUp, 0x5),
+        (Button::DPadDown, 0x8),
+        (Button::DPadLeft, 0x7),
+        (Button::DPadRight, 0x9),
+        (Button::South, 0x6),
+        (Button::East, 0x4),
+        (Button::West, 0xA),
+        (Button::North, 0xB),
+        (Button::Select, 0xE),
+        (Button::Start, 0xF),
+    ])
+}
+
+/// NES standard controller buttons
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum NesButton {
+    A,
+    B,
+    Select,
+    Start,
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+impl NesButton {
+    /// bit in the controller shift register, read out from bit 0
+    pub fn bit(&self) -> u8 {
+        1 << *self as u8
+    }
+}
+
+/// nintendo layout, A on the right face button
+pub fn nes() -> Bindings<NesButton> {
+    Bindings::new([
+        (Button::East, NesButton::A),
+        (Button::South, NesButton::B),
+        (Button::Select, NesButton::Select),
+        (Button::Start, NesButton::Start),
+        (Button::DPadUp, NesButton::Up),
+        (Button::DPadDown, NesButton::Down),
+        (Button::DPadLeft, NesButton::Left),
+        (Button::DPadRight, NesButton::Right),
+    ])
+}
+
+/// NesJoypad
+///
+/// pressed buttons of a standard controller in shift register order, the
+/// byte latched on a $4016 strobe.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct NesJoypad(pub u8);
+
+impl NesJoypad {
+    pub fn set(&mut self, button: NesButton, pressed: bool) {
+        if pressed {
+            self.0 |= button.bit();
+        } else {
+            self.0 &= !button.bit();
+        }
+    }
+}
+
+/// Game Boy joypad buttons
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GbButton {
+    Right,
+    Left,
+    Up,
+    Down,
+    A,
+    B,
+    Select,
+    Start,
+}
+
+pub fn gameboy() -> Bindings<GbButton> {
+    Bindings::new([
+        (Button::East, GbButton::A),
+        (Button::South, GbButton::B),
+        (Button::Select, GbButton::Select),
+        (Button::Start, GbButton::Start),
+        (Button::DPadUp, GbButton::Up),
+        (Button::DPadDown, GbButton::Down),
+        (Button::DPadLeft, GbButton::Left),
+        (Button::DPadRight, GbButton::Right),
+    ])
+}
+
+/// GbJoypad
+///
+/// pressed buttons as the two nibbles the P1 register ($FF00) selects
+/// between, bit set while pressed.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct GbJoypad {
+    /// right, left, up, down from bit 0
+    pub dpad: u8,
+    /// A, B, select, start from bit 0
+    pub buttons: u8,
+}
+
+impl GbJoypad {
+    pub fn set(&mut self, button: GbButton, pressed: bool) {
+        let n = button as u8;
+        let (group, bit) = if n < 4 {
+            (&mut self.dpad, 1 << n)
+        } else {
+            (&mut self.buttons, 1 << (n - 4))
+        };
+        if pressed {
+            *group |= bit;
+        } else {
+            *group &= !bit;
+        }
+    }
+
+    /// P1 as read back after writing `select`, bits 4 and 5 pick the
+    /// dpad and buttons when low, and pressed lines read low
+    pub fn p1(&self, select: u8) -> u8 {
+        let mut pressed = 0;
+        if select & 0x10 == 0 {
+            pressed |= self.dpad;
+        }
+        if select & 0x20 == 0 {
+            pressed |= self.buttons;
+        }
+        0xC0 | (select & 0x30) | (!pressed & 0x0F)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn profiles_map_events_to_each_core() {
+        let events = [
+            Event::press(Button::DPadUp),
+            Event::press(Button::East),
+            Event::press(Button::Mode),
+            Event::release(Button::DPadUp),
+        ];
+
+        let mut keys = [false; 16];
+        chip8().apply(&events, |k, p| keys[k as usize] = p);
+        assert!(!keys[0x5] && keys[0x4]);
+
+        let mut nes_pad = NesJoypad::default();
+        nes().apply(&events, |b, p| nes_pad.set(b, p));
+        assert_eq!(nes_pad.0, NesButton::A.bit());
+
+        let mut gb = GbJoypad::default();
+        gameboy().apply(&events, |b, p| gb.set(b, p));
+        assert_eq!(gb.p1(0x10), 0xDE);
+        assert_eq!(gb.p1(0x20), 0xEF);
+        assert_eq!(gb.p1(0x30), 0xFF);
+    }
+
+    #[test]
+    fn rebinding_replaces_the_target() {
+        let mut b = chip8();
+        b.bind(Button::South, 0x5);
+        b.unbind(Button::Start);
+        assert_eq!(
+            (b.get(Button::South), b.get(Button::Start)),
+            (Some(0x5), None)
+        );
+    }
+}
@@ -0,0 +1,111 @@
+use std::{collections::VecDeque, path::Path};
+
+use color_eyre::{
+    eyre::{bail, eyre, WrapErr},
+    Result,
+};
+
+use super::{Button, Event, InputSource};
+
+/// ScriptedInput
+///
+/// events replayed at fixed frames, for tests and reproducible runs. each
+/// poll is one frame, starting from frame 0. scripts are lines of
+///
+/// ```text
+/// # frame button state
+/// 10 south down
+/// 14 south up
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct ScriptedInput {
+    frame: u64,
+    /// (frame, event) in frame order
+    events: VecDeque<(u64, Event)>,
+}
+
+impl ScriptedInput {
+    pub fn new(events: impl IntoIterator<Item = (u64, Event)>) -> Self {
+        let mut events: Vec<_> = events.into_iter().collect();
+        events.sort_by_key(|(frame, _)| *frame);
+        Self {
+            frame: 0,
+            events: events.into(),
+        }
+    }
+
+    pub fn parse(src: &str) -> Result<Self> {
+        let mut events = vec![];
+        for (n, line) in src.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or_default().trim();
+            if line.is_empty() {
+                continue;
+            }
+            let event = || -> Result<(u64, Event)> {
+                let [frame, button, state] = line.split_whitespace().collect::<Vec<_>>()[..] else {
+                    bail!("expected `<frame> <button> <down|up>`");
+                };
+                let frame = frame.parse().map_err(|_| eyre!("bad frame `{frame}`"))?;
+                let button: Button = button.parse()?;
+                let pressed = match state {
+                    "down" => true,
+                    "up" => false,
+                    _ => bail!("expected down or up, not `{state}`"),
+                };
+                Ok((frame, Event { button, pressed }))
+            };
+            events.push(event().wrap_err_with(|| format!("line {}", n + 1))?);
+        }
+        Ok(Self::new(events))
+    }
+
+    pub fn load_from_path(path: impl AsRef<Path>) -> Result<Self> {
+        Self::parse(&std::fs::read_to_string(path)?)
+    }
+
+    /// the script has run out of events
+    pub fn finished(&self) -> bool {
+        self.events.is_empty()
+    }
+}
+
+impl InputSource for ScriptedInput {
+    fn poll(&mut self) -> Result<Vec<Event>> {
+        let mut due = vec![];
+        while self.events.front().is_some_and(|(f, _)| *f <= self.frame) {
+            due.extend(self.events.pop_front().map(|(_, e)| e));
+        }
+        self.frame += 1;
+        Ok(due)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn replays_on_the_scripted_frames() {
+        let mut script = ScriptedInput::parse(
+            "# jump
+            1 south down
+            1 dpad_left down # at once
+            3 south up",
+        )
+        .unwrap();
+        assert_eq!(script.poll().unwrap(), []);
+        assert_eq!(
+            script.poll().unwrap(),
+            [Event::press(Button::South), Event::press(Button::DPadLeft)]
+        );
+        assert_eq!(script.poll().unwrap(), []);
+        assert_eq!(script.poll().unwrap(), [Event::release(Button::South)]);
+        assert!(script.finished());
+    }
+
+    #[test]
+    fn errors_name_the_line() {
+        let err = ScriptedInput::parse("1 south down\n2 kick down").unwrap_err();
+        assert_eq!(format!("{err:#}"), "line 2: unknown button `kick`");
+    }
+}
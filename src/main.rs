@@ -13,7 +13,7 @@ struct App {
 #[derive(Subcommand)]
 enum Commands {
     Nes(res::App),
-    Chip8(chiprs::App),
+    Chip8(Box<chiprs::App>),
     Gameboy(gamebors::App),
 }
 
@@ -25,6 +25,8 @@ pub fn main() -> Result<()> {
         .with_line_number(true)
         .with_thread_names(true)
         .with_ansi(true)
+        // keep stdout clean for disassembly output
+        .with_writer(std::io::stderr)
         .with_env_filter({
             let env_builder = EnvFilter::builder();
             #[cfg(debug_assertions)]
@@ -37,17 +39,25 @@ pub fn main() -> Result<()> {
     match app.subcommands {
         Commands::Nes(nes) => nes.start(),
         Commands::Chip8(mut chip8) => {
+            if let Some(command) = chip8.command.take() {
+                return command.run();
+            }
+            if chip8.debug {
+                return chip8.debug_rom();
+            }
             if chip8.disassemble {
-                chip8.disassemble_rom();
+                chip8.disassemble_rom()?;
                 return Ok(());
             }
             info!(
-                rom_path = chip8.rom.display().to_string(),
+                rom_path = chip8.rom()?.display().to_string(),
                 "chiprs emulator: "
             );
             chip8.init()?;
             let ev = EventLoop::new()?;
-            _ = ev.run_app(&mut chip8);
+            _ = ev.run_app(&mut *chip8);
+            chip8.save_movie()?;
+            chip8.flush_audio()?;
         }
         Commands::Gameboy(gb) => gb.start(),
     }